@@ -0,0 +1,58 @@
+// fixed physics timestep in seconds
+pub const TIMESTEP: f32 = 1.0 / 200.0;
+
+// upper bound for the time simulated per frame, a stalled frame is not caught up beyond it
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// turns real elapsed time into a number of fixed simulation steps,
+// the remainder is carried over to the next frame
+pub struct Clock {
+    timestep: f32,
+    accumulator: f32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(TIMESTEP)
+    }
+}
+
+impl Clock {
+    pub fn new(timestep: f32) -> Clock {
+        Clock {
+            timestep,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    // add `elapsed` seconds of real time, returns how many steps to simulate
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed.min(MAX_FRAME_TIME);
+        let steps = (self.accumulator / self.timestep).floor();
+        self.accumulator -= steps * self.timestep;
+        steps as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carries_the_remainder_over() {
+        let mut clock = Clock::new(0.125);
+        assert_eq!(clock.advance(0.1875), 1);
+        // the remaining 0.0625 s add up with the next frames
+        assert_eq!(clock.advance(0.0), 0);
+        assert_eq!(clock.advance(0.0625), 1);
+        assert_eq!(clock.advance(0.0625), 0);
+        assert_eq!(clock.advance(0.0625), 1);
+        // a stalled frame is only caught up to the maximum frame time
+        assert_eq!(clock.advance(10.0), 2);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+}
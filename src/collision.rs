@@ -0,0 +1,358 @@
+use crate::geometry::{polygon_area, Vec2};
+use crate::robot::Part;
+
+// contact of two polygons
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contact {
+    // unit normal pointing from the first polygon to the second
+    pub normal: Vec2,
+    // deepest penetration along the normal
+    pub depth: f32,
+    // contact points with their penetration depth, they lie on the face that is pressed into the
+    // other polygon
+    pub points: Vec<(Vec2, f32)>,
+}
+
+pub fn collide_parts(a: &dyn Part, b: &dyn Part) -> Option<Contact> {
+    collide(a.get_vertices_ref(), b.get_vertices_ref())
+}
+
+// contact of two polygons of any shape, concave polygons are split into convex pieces and the
+// normal is the one of the deepest pair of pieces
+pub fn collide(a: &[Vec2], b: &[Vec2]) -> Option<Contact> {
+    if a.len() < 3 || b.len() < 3 || !bounds_overlap(a, b) {
+        return None;
+    }
+    let (pieces_a, pieces_b) = (convex_pieces(a), convex_pieces(b));
+    let mut contact: Option<Contact> = None;
+    for piece_a in pieces_a.iter() {
+        for piece_b in pieces_b.iter() {
+            let Some(found) = convex_contact(piece_a, piece_b) else {
+                continue;
+            };
+            contact = Some(match contact {
+                None => found,
+                Some(mut contact) => {
+                    if found.depth > contact.depth {
+                        contact.normal = found.normal;
+                        contact.depth = found.depth;
+                    }
+                    contact.points.extend(found.points);
+                    contact
+                }
+            });
+        }
+    }
+    contact
+}
+
+// contact of two convex polygons by separating axes, the points are the face of one polygon
+// clipped to the face of the other it is pressed into
+pub fn convex_contact(a: &[Vec2], b: &[Vec2]) -> Option<Contact> {
+    let (face_a, separation_a) = deepest_face(a, b)?;
+    let (face_b, separation_b) = deepest_face(b, a)?;
+    // prefer `a` to keep the reference face from flipping between steps
+    let (reference, incident, face, flip) = if separation_b > separation_a + 1e-4 {
+        (b, a, face_b, true)
+    } else {
+        (a, b, face_a, false)
+    };
+
+    let (start, end) = edge(reference, face);
+    let normal = outward_normal(reference, face);
+    let depth_of = |point: Vec2| -(point - start).dot(normal);
+    // face of the other polygon facing the reference face the most
+    let (mut first, mut second) = edge(
+        incident,
+        (0..incident.len())
+            .min_by(|i, j| {
+                let (ni, nj) = (outward_normal(incident, *i), outward_normal(incident, *j));
+                ni.dot(normal).total_cmp(&nj.dot(normal))
+            })
+            .unwrap(),
+    );
+
+    // clip the incident face to the sides of the reference face
+    let tangent = end - start;
+    let mut clipped = true;
+    for (origin, direction) in [(start, -tangent), (end, tangent)] {
+        let (d1, d2) = (
+            (first - origin).dot(direction),
+            (second - origin).dot(direction),
+        );
+        if d1 > 0.0 && d2 > 0.0 {
+            clipped = false;
+            break;
+        }
+        if d1 > 0.0 {
+            first = first + (second - first) * (d1 / (d1 - d2));
+        } else if d2 > 0.0 {
+            second = second + (first - second) * (d2 / (d2 - d1));
+        }
+    }
+    let mut points: Vec<(Vec2, f32)> = if clipped {
+        [first, second]
+            .into_iter()
+            .map(|point| (point, depth_of(point)))
+            .filter(|(_, depth)| *depth > 0.0)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if points.is_empty() {
+        // the faces do not line up, the deepest vertex is the contact
+        let deepest = incident
+            .iter()
+            .map(|v| (*v, depth_of(*v)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        points.push(deepest);
+    }
+    let depth = points.iter().map(|(_, depth)| *depth).fold(0.0, f32::max);
+    Some(Contact {
+        normal: if flip { -normal } else { normal },
+        depth,
+        points,
+    })
+}
+
+pub fn is_convex(vertices: &[Vec2]) -> bool {
+    let n = vertices.len();
+    let sign = polygon_area(vertices).signum();
+    (0..n).all(|i| {
+        let (a, b, c) = (vertices[i], vertices[(i + 1) % n], vertices[(i + 2) % n]);
+        (b - a).cross(c - b) * sign >= -1e-9
+    })
+}
+
+// counter-clockwise hull of the points
+pub fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    // lower and upper chain of the monotone chain algorithm
+    let mut hull: Vec<Vec2> = Vec::with_capacity(sorted.len() + 1);
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && (hull[hull.len() - 1] - hull[hull.len() - 2]).cross(point - hull[hull.len() - 1])
+                    <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+// convex polygons covering `vertices`, concave polygons are split into triangles and polygons
+// whose edges cross are replaced by their hull
+pub fn convex_pieces(vertices: &[Vec2]) -> Vec<Vec<Vec2>> {
+    if is_convex(vertices) {
+        return vec![vertices.to_vec()];
+    }
+    if crosses_itself(vertices) {
+        return vec![convex_hull(vertices)];
+    }
+
+    // ear clipping on the counter-clockwise polygon
+    let mut polygon = vertices.to_vec();
+    if polygon_area(&polygon) < 0.0 {
+        polygon.reverse();
+    }
+    let mut pieces = Vec::new();
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let corner = |i: usize| (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+        let ear = (0..n).find(|i| {
+            let (prev, vertex, next) = corner(*i);
+            (vertex - prev).cross(next - vertex) > 0.0
+                && !polygon.iter().any(|p| {
+                    *p != prev && *p != vertex && *p != next && in_triangle(*p, prev, vertex, next)
+                })
+        });
+        let Some(ear) = ear else {
+            // only degenerate corners are left
+            break;
+        };
+        let (prev, vertex, next) = corner(ear);
+        pieces.push(vec![prev, vertex, next]);
+        polygon.remove(ear);
+    }
+    let rest = convex_hull(&polygon);
+    if rest.len() >= 3 {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+// face of `vertices` the other polygon penetrates the least and the signed separation along its
+// normal, none if that face separates the polygons
+fn deepest_face(vertices: &[Vec2], other: &[Vec2]) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+    for i in 0..vertices.len() {
+        let (start, end) = edge(vertices, i);
+        if (end - start).length() < 1e-9 {
+            continue;
+        }
+        let normal = outward_normal(vertices, i);
+        let separation = other
+            .iter()
+            .map(|v| (*v - start).dot(normal))
+            .fold(f32::INFINITY, f32::min);
+        if separation > 0.0 {
+            return None;
+        }
+        if best.is_none_or(|(_, best)| separation > best) {
+            best = Some((i, separation));
+        }
+    }
+    best
+}
+
+fn edge(vertices: &[Vec2], i: usize) -> (Vec2, Vec2) {
+    (vertices[i], vertices[(i + 1) % vertices.len()])
+}
+
+// unit normal of face `i` pointing away from the polygon, for either winding
+fn outward_normal(vertices: &[Vec2], i: usize) -> Vec2 {
+    let (start, end) = edge(vertices, i);
+    let edge = end - start;
+    let normal = edge.perp() * (1.0 / edge.length().max(1e-9));
+    if polygon_area(vertices) > 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+fn bounds_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    let bounds = |vertices: &[Vec2]| {
+        vertices.iter().fold(
+            (
+                Vec2::new(f32::INFINITY, f32::INFINITY),
+                Vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), v| {
+                (
+                    Vec2::new(min.x.min(v.x), min.y.min(v.y)),
+                    Vec2::new(max.x.max(v.x), max.y.max(v.y)),
+                )
+            },
+        )
+    };
+    let ((min_a, max_a), (min_b, max_b)) = (bounds(a), bounds(b));
+    min_a.x <= max_b.x && min_b.x <= max_a.x && min_a.y <= max_b.y && min_b.y <= max_a.y
+}
+
+// whether two edges of the polygon that do not share a vertex cross
+fn crosses_itself(vertices: &[Vec2]) -> bool {
+    let n = vertices.len();
+    (0..n).any(|i| {
+        (i + 2..n)
+            .filter(|j| (j + 1) % n != i)
+            .any(|j| segments_intersect(edge(vertices, i), edge(vertices, j)))
+    })
+}
+
+fn segments_intersect((a, b): (Vec2, Vec2), (c, d): (Vec2, Vec2)) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).cross(r - p);
+    side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+}
+
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).cross(point - a) >= 0.0
+        && (c - b).cross(point - b) >= 0.0
+        && (a - c).cross(point - c) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::CellDescription;
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(x, y),
+            Vec2::new(x + size, y),
+            Vec2::new(x + size, y + size),
+            Vec2::new(x, y + size),
+        ]
+    }
+
+    fn table() -> Vec<Vec2> {
+        CellDescription::default()
+            .fixtures
+            .into_iter()
+            .find(|fixture| fixture.name == "table")
+            .unwrap()
+            .vertices
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let (a, b) = (square(0.0, 0.0, 1.0), square(0.8, 0.1, 1.0));
+        let contact = collide(&a, &b).unwrap();
+        assert!(close(contact.depth, 0.2));
+        assert!(close(contact.normal.x, 1.0) && close(contact.normal.y, 0.0));
+        // the clipped face of `b` inside `a`
+        assert_eq!(contact.points.len(), 2);
+        for (point, depth) in contact.points.iter() {
+            assert!(close(point.x, 0.8) && (0.1 - 1e-5..=1.0 + 1e-5).contains(&point.y));
+            assert!(close(*depth, 0.2));
+        }
+
+        // the normal points from the first polygon to the second for either order and winding
+        let reversed: Vec<Vec2> = a.iter().rev().copied().collect();
+        let contact = collide(&b, &reversed).unwrap();
+        assert!(close(contact.depth, 0.2));
+        assert!(close(contact.normal.x, -1.0) && close(contact.normal.y, 0.0));
+    }
+
+    #[test]
+    fn separated_polygons() {
+        assert_eq!(
+            collide(&square(0.0, 0.0, 1.0), &square(1.5, 0.0, 1.0)),
+            None
+        );
+        // the bounds overlap but a separating axis runs between the triangles
+        let a = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        let b = [
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.6, 1.0),
+            Vec2::new(1.0, 0.6),
+        ];
+        assert_eq!(convex_contact(&a, &b), None);
+        assert_eq!(collide(&a, &b), None);
+    }
+
+    #[test]
+    fn table_pieces() {
+        let table = table();
+        assert!(!is_convex(&table));
+        let pieces = convex_pieces(&table);
+        assert!(pieces.len() > 1);
+        assert!(pieces.iter().all(|piece| is_convex(piece)));
+        let area: f32 = pieces.iter().map(|piece| polygon_area(piece).abs()).sum();
+        assert!(close(area, polygon_area(&table).abs()));
+
+        // a box between the legs stays clear of the table, one on the top sinks in from above
+        assert_eq!(collide(&table, &square(0.4, -0.43, 0.1)), None);
+        let contact = collide(&table, &square(0.4, -0.23, 0.1)).unwrap();
+        assert!(close(contact.depth, 0.01));
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, 1.0));
+    }
+}
@@ -0,0 +1,631 @@
+use crate::geometry::Vec2;
+use crate::robot::{
+    ArmDescription, CellDescription, ClawDescription, Color, FixtureDescription, LinkDescription,
+    ObjectDescription, DEF_FIXTURE_COLOR, DEF_OBJECT_MASS,
+};
+use crate::servo::ServoDescription;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// description file loaded at startup when no other file is given
+pub const DEFAULT_PATH: &str = "robot.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    // semantic error, `field` is the path to the offending value, e.g. `arm.links[1].length`
+    Invalid {
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, message } => {
+                write!(f, "cannot parse {}: {}", path.display(), message)
+            }
+            ConfigError::Invalid { field, message } => write!(f, "{}: {}", field, message),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+fn invalid(field: String, message: &str) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        message: message.to_string(),
+    }
+}
+
+// layout of the description file, missing values fall back to the built-in arm and cell
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RobotFile {
+    pub arm: ArmFile,
+    pub cell: CellFile,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArmFile {
+    pub base: [f32; 2],
+    pub links: Vec<LinkFile>,
+    pub claw: ClawFile,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinkFile {
+    pub length: f32,
+    pub width: f32,
+    pub joint_radius: f32,
+    pub color: Color,
+    pub limits: [f32; 2],
+    pub rest: f32,
+    pub max_velocity: f32,
+    pub max_acceleration: f32,
+    pub max_jerk: f32,
+    pub mass: f32,
+    pub center_of_mass: f32,
+    pub inertia: f32,
+    pub max_torque: f32,
+    pub jog_increment: f32,
+    pub servo: ServoFile,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServoFile {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    pub gear_ratio: f32,
+    pub torque_constant: f32,
+    pub resistance: f32,
+    pub voltage: f32,
+    pub friction: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClawFile {
+    pub length: f32,
+    pub depth: f32,
+    pub opening: f32,
+    pub color: Color,
+    pub max_velocity: f32,
+    pub jog_increment: f32,
+    pub grip_force: f32,
+    pub friction: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CellFile {
+    pub ground: f32,
+    pub objects: Vec<ObjectFile>,
+    pub fixtures: Vec<FixtureFile>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjectFile {
+    pub name: String,
+    pub vertices: Vec<[f32; 2]>,
+    pub color: Color,
+    pub mass: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixtureFile {
+    pub name: String,
+    pub vertices: Vec<[f32; 2]>,
+    pub color: Color,
+}
+
+impl From<&LinkDescription> for LinkFile {
+    fn from(link: &LinkDescription) -> Self {
+        LinkFile {
+            length: link.length,
+            width: link.width,
+            joint_radius: link.joint_radius,
+            color: link.color,
+            limits: [link.limits.0, link.limits.1],
+            rest: link.rest,
+            max_velocity: link.max_velocity,
+            max_acceleration: link.max_acceleration,
+            max_jerk: link.max_jerk,
+            mass: link.mass,
+            center_of_mass: link.center_of_mass,
+            inertia: link.inertia,
+            max_torque: link.max_torque,
+            jog_increment: link.jog_increment,
+            servo: ServoFile::from(&link.servo),
+        }
+    }
+}
+
+impl From<&ServoDescription> for ServoFile {
+    fn from(servo: &ServoDescription) -> Self {
+        ServoFile {
+            kp: servo.kp,
+            ki: servo.ki,
+            kd: servo.kd,
+            gear_ratio: servo.gear_ratio,
+            torque_constant: servo.torque_constant,
+            resistance: servo.resistance,
+            voltage: servo.voltage,
+            friction: servo.friction,
+        }
+    }
+}
+
+impl Default for ServoFile {
+    fn default() -> Self {
+        ServoFile::from(&ServoDescription::default())
+    }
+}
+
+impl Default for ArmFile {
+    fn default() -> Self {
+        let arm = ArmDescription::default();
+        ArmFile {
+            base: [arm.base.x, arm.base.y],
+            links: arm.links.iter().map(LinkFile::from).collect(),
+            claw: ClawFile::default(),
+        }
+    }
+}
+
+impl Default for LinkFile {
+    fn default() -> Self {
+        LinkFile::from(&ArmDescription::default().links[0])
+    }
+}
+
+impl Default for ClawFile {
+    fn default() -> Self {
+        let claw = ClawDescription::default();
+        ClawFile {
+            length: claw.length,
+            depth: claw.depth,
+            opening: claw.opening,
+            color: claw.color,
+            max_velocity: claw.max_velocity,
+            jog_increment: claw.jog_increment,
+            grip_force: claw.grip_force,
+            friction: claw.friction,
+        }
+    }
+}
+
+impl Default for ObjectFile {
+    fn default() -> Self {
+        ObjectFile {
+            name: String::new(),
+            vertices: Vec::new(),
+            color: [0.0, 0.0, 0.0],
+            mass: DEF_OBJECT_MASS,
+        }
+    }
+}
+
+impl Default for FixtureFile {
+    fn default() -> Self {
+        FixtureFile {
+            name: String::new(),
+            vertices: Vec::new(),
+            color: DEF_FIXTURE_COLOR,
+        }
+    }
+}
+
+impl Default for CellFile {
+    fn default() -> Self {
+        let cell = CellDescription::default();
+        CellFile {
+            ground: cell.ground,
+            objects: cell
+                .objects
+                .iter()
+                .map(|object| ObjectFile {
+                    name: object.name.clone(),
+                    vertices: object.vertices.iter().map(|v| [v.x, v.y]).collect(),
+                    color: object.color,
+                    mass: object.mass,
+                })
+                .collect(),
+            fixtures: cell
+                .fixtures
+                .iter()
+                .map(|fixture| FixtureFile {
+                    name: fixture.name.clone(),
+                    vertices: fixture.vertices.iter().map(|v| [v.x, v.y]).collect(),
+                    color: fixture.color,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn check_finite(field: String, value: f32) -> Result<f32, ConfigError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(invalid(field, "must be a finite number"))
+    }
+}
+
+fn check_positive(field: String, value: f32) -> Result<f32, ConfigError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(invalid(field, "must be greater than 0"))
+    }
+}
+
+fn check_non_negative(field: String, value: f32) -> Result<f32, ConfigError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(invalid(field, "must not be negative"))
+    }
+}
+
+// strictly between `min` and `max`
+fn check_between(field: String, value: f32, (min, max): (f32, f32)) -> Result<f32, ConfigError> {
+    if value > min && value < max {
+        Ok(value)
+    } else {
+        Err(ConfigError::Invalid {
+            field,
+            message: format!("must be between {} and {}", min, max),
+        })
+    }
+}
+
+fn check_point(field: String, [x, y]: [f32; 2]) -> Result<Vec2, ConfigError> {
+    if x.is_finite() && y.is_finite() {
+        Ok(Vec2::new(x, y))
+    } else {
+        Err(invalid(field, "coordinates must be finite numbers"))
+    }
+}
+
+fn check_color(field: String, color: Color) -> Result<Color, ConfigError> {
+    if color.iter().all(|c| (0.0..=1.0).contains(c)) {
+        Ok(color)
+    } else {
+        Err(invalid(field, "components must be between 0 and 1"))
+    }
+}
+
+impl LinkFile {
+    fn validate(&self, field: &str) -> Result<LinkDescription, ConfigError> {
+        let [min, max] = self.limits;
+        check_finite(format!("{}.limits", field), min)?;
+        check_finite(format!("{}.limits", field), max)?;
+        if min > max {
+            return Err(invalid(
+                format!("{}.limits", field),
+                "lower limit is greater than the upper limit",
+            ));
+        }
+        if !(min..=max).contains(&self.rest) {
+            return Err(invalid(
+                format!("{}.rest", field),
+                "must be within the joint limits",
+            ));
+        }
+
+        Ok(LinkDescription {
+            length: check_positive(format!("{}.length", field), self.length)?,
+            width: check_positive(format!("{}.width", field), self.width)?,
+            joint_radius: check_positive(format!("{}.joint_radius", field), self.joint_radius)?,
+            color: check_color(format!("{}.color", field), self.color)?,
+            limits: (min, max),
+            rest: self.rest,
+            max_velocity: check_positive(format!("{}.max_velocity", field), self.max_velocity)?,
+            max_acceleration: check_positive(
+                format!("{}.max_acceleration", field),
+                self.max_acceleration,
+            )?,
+            max_jerk: check_positive(format!("{}.max_jerk", field), self.max_jerk)?,
+            mass: check_positive(format!("{}.mass", field), self.mass)?,
+            center_of_mass: check_finite(format!("{}.center_of_mass", field), self.center_of_mass)?,
+            inertia: check_non_negative(format!("{}.inertia", field), self.inertia)?,
+            max_torque: check_positive(format!("{}.max_torque", field), self.max_torque)?,
+            servo: self.servo.validate(&format!("{}.servo", field))?,
+            jog_increment: check_positive(format!("{}.jog_increment", field), self.jog_increment)?,
+        })
+    }
+}
+
+impl ServoFile {
+    fn validate(&self, field: &str) -> Result<ServoDescription, ConfigError> {
+        Ok(ServoDescription {
+            kp: check_non_negative(format!("{}.kp", field), self.kp)?,
+            ki: check_non_negative(format!("{}.ki", field), self.ki)?,
+            kd: check_non_negative(format!("{}.kd", field), self.kd)?,
+            gear_ratio: check_positive(format!("{}.gear_ratio", field), self.gear_ratio)?,
+            torque_constant: check_positive(
+                format!("{}.torque_constant", field),
+                self.torque_constant,
+            )?,
+            resistance: check_positive(format!("{}.resistance", field), self.resistance)?,
+            voltage: check_positive(format!("{}.voltage", field), self.voltage)?,
+            friction: check_non_negative(format!("{}.friction", field), self.friction)?,
+        })
+    }
+}
+
+impl ArmFile {
+    fn validate(&self) -> Result<ArmDescription, ConfigError> {
+        if self.links.is_empty() {
+            return Err(invalid("arm.links".to_string(), "needs at least one link"));
+        }
+        let links = self
+            .links
+            .iter()
+            .enumerate()
+            .map(|(i, link)| link.validate(&format!("arm.links[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let claw = ClawDescription {
+            length: check_positive("arm.claw.length".to_string(), self.claw.length)?,
+            depth: check_non_negative("arm.claw.depth".to_string(), self.claw.depth)?,
+            opening: check_between(
+                "arm.claw.opening".to_string(),
+                self.claw.opening,
+                (0.0, 180.0),
+            )?,
+            color: check_color("arm.claw.color".to_string(), self.claw.color)?,
+            max_velocity: check_positive(
+                "arm.claw.max_velocity".to_string(),
+                self.claw.max_velocity,
+            )?,
+            jog_increment: check_positive(
+                "arm.claw.jog_increment".to_string(),
+                self.claw.jog_increment,
+            )?,
+            grip_force: check_non_negative(
+                "arm.claw.grip_force".to_string(),
+                self.claw.grip_force,
+            )?,
+            friction: check_non_negative("arm.claw.friction".to_string(), self.claw.friction)?,
+        };
+
+        Ok(ArmDescription {
+            base: check_point("arm.base".to_string(), self.base)?,
+            links,
+            claw,
+        })
+    }
+}
+
+impl CellFile {
+    fn validate(&self) -> Result<CellDescription, ConfigError> {
+        let mut objects: Vec<ObjectDescription> = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            let field = format!("cell.objects[{}]", i);
+            if object.name.is_empty() {
+                return Err(invalid(format!("{}.name", field), "must not be empty"));
+            }
+            if objects.iter().any(|other| other.name == object.name) {
+                return Err(invalid(
+                    format!("{}.name", field),
+                    "is already used by another object",
+                ));
+            }
+            if object.vertices.len() < 3 {
+                return Err(invalid(
+                    format!("{}.vertices", field),
+                    "needs at least 3 vertices",
+                ));
+            }
+            objects.push(ObjectDescription {
+                name: object.name.clone(),
+                vertices: object
+                    .vertices
+                    .iter()
+                    .enumerate()
+                    .map(|(j, v)| check_point(format!("{}.vertices[{}]", field, j), *v))
+                    .collect::<Result<_, _>>()?,
+                color: check_color(format!("{}.color", field), object.color)?,
+                mass: check_positive(format!("{}.mass", field), object.mass)?,
+            });
+        }
+
+        let mut fixtures: Vec<FixtureDescription> = Vec::new();
+        for (i, fixture) in self.fixtures.iter().enumerate() {
+            let field = format!("cell.fixtures[{}]", i);
+            if fixture.name.is_empty() {
+                return Err(invalid(format!("{}.name", field), "must not be empty"));
+            }
+            if fixtures.iter().any(|other| other.name == fixture.name) {
+                return Err(invalid(
+                    format!("{}.name", field),
+                    "is already used by another fixture",
+                ));
+            }
+            if fixture.vertices.len() < 3 {
+                return Err(invalid(
+                    format!("{}.vertices", field),
+                    "needs at least 3 vertices",
+                ));
+            }
+            fixtures.push(FixtureDescription {
+                name: fixture.name.clone(),
+                vertices: fixture
+                    .vertices
+                    .iter()
+                    .enumerate()
+                    .map(|(j, v)| check_point(format!("{}.vertices[{}]", field, j), *v))
+                    .collect::<Result<_, _>>()?,
+                color: check_color(format!("{}.color", field), fixture.color)?,
+            });
+        }
+
+        Ok(CellDescription {
+            ground: check_finite("cell.ground".to_string(), self.ground)?,
+            objects,
+            fixtures,
+        })
+    }
+}
+
+impl RobotFile {
+    pub fn validate(&self) -> Result<(ArmDescription, CellDescription), ConfigError> {
+        Ok((self.arm.validate()?, self.cell.validate()?))
+    }
+}
+
+// load a robot and cell description, `.json` files are read as JSON and everything else as TOML
+pub fn load(path: &Path) -> Result<(ArmDescription, CellDescription), ConfigError> {
+    let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(path, &text)
+}
+
+// description in `text`, `path` selects the format and names the file in errors
+fn parse(path: &Path, text: &str) -> Result<(ArmDescription, CellDescription), ConfigError> {
+    let parse_error = |message: String| ConfigError::Parse {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let file: RobotFile = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(text).map_err(|e| parse_error(e.to_string()))?
+    } else {
+        toml::from_str(text).map_err(|e| parse_error(e.to_string()))?
+    };
+    file.validate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_toml(text: &str) -> Result<(ArmDescription, CellDescription), ConfigError> {
+        parse(Path::new("robot.toml"), text)
+    }
+
+    fn parse_json(text: &str) -> Result<(ArmDescription, CellDescription), ConfigError> {
+        parse(Path::new("robot.json"), text)
+    }
+
+    fn invalid_field(result: Result<(ArmDescription, CellDescription), ConfigError>) -> String {
+        match result {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            result => panic!("expected an invalid field, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn empty_file_is_the_built_in_robot() {
+        let (arm, cell) = parse_toml("").unwrap();
+        assert_eq!(arm, ArmDescription::default());
+        assert_eq!(cell, CellDescription::default());
+        assert_eq!(parse_json("{}").unwrap(), (arm, cell));
+    }
+
+    #[test]
+    fn missing_link_values_are_the_built_in_ones() {
+        let (arm, _) = parse_toml("[[arm.links]]\nlength = 0.3\n").unwrap();
+        assert_eq!(arm.links.len(), 1);
+        assert_eq!(arm.links[0].length, 0.3);
+        assert_eq!(
+            arm.links[0].limits,
+            ArmDescription::default().links[0].limits
+        );
+    }
+
+    #[test]
+    fn reports_the_invalid_field() {
+        let toml = "[[arm.links]]\n[[arm.links]]\nlength = 0.0\n[[arm.links]]\n";
+        assert_eq!(invalid_field(parse_toml(toml)), "arm.links[1].length");
+        let json = r#"{"arm": {"links": [{}, {"length": -0.4}, {}]}}"#;
+        assert_eq!(invalid_field(parse_json(json)), "arm.links[1].length");
+        let json = r#"{"arm": {"claw": {"opening": 180.0}}}"#;
+        assert_eq!(invalid_field(parse_json(json)), "arm.claw.opening");
+        let toml = "[arm.claw]\ndepth = -0.01\n";
+        assert_eq!(invalid_field(parse_toml(toml)), "arm.claw.depth");
+    }
+
+    #[test]
+    fn rejects_rest_outside_of_limits() {
+        let toml = "[[arm.links]]\nlimits = [-90.0, 0.0]\nrest = 10.0\n";
+        match parse_toml(toml) {
+            Err(ConfigError::Invalid { field, message }) => {
+                assert_eq!(field, "arm.links[0].rest");
+                assert_eq!(message, "must be within the joint limits");
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let toml = r#"
+            [[cell.objects]]
+            name = "box"
+            vertices = [[0.0, 0.0], [0.1, 0.0], [0.1, 0.1]]
+
+            [[cell.objects]]
+            name = "box"
+            vertices = [[0.2, 0.0], [0.3, 0.0], [0.3, 0.1]]
+        "#;
+        match parse_toml(toml) {
+            Err(ConfigError::Invalid { field, message }) => {
+                assert_eq!(field, "cell.objects[1].name");
+                assert_eq!(message, "is already used by another object");
+            }
+            result => panic!("{:?}", result),
+        }
+        let json = r#"{"cell": {"fixtures": [
+            {"name": "table", "vertices": [[0.0, 0.0], [0.1, 0.0], [0.1, 0.1]]},
+            {"name": "table", "vertices": [[0.2, 0.0], [0.3, 0.0], [0.3, 0.1]]}
+        ]}}"#;
+        assert_eq!(invalid_field(parse_json(json)), "cell.fixtures[1].name");
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        for result in [
+            parse_toml("[arm]\nlenght = 0.4\n"),
+            parse_json(r#"{"arm": {"lenght": 0.4}}"#),
+        ] {
+            match result {
+                Err(ConfigError::Parse { message, .. }) => {
+                    assert!(message.contains("unknown field `lenght`"), "{}", message)
+                }
+                result => panic!("{:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn loads_the_default_file() {
+        load(Path::new(DEFAULT_PATH)).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+use crate::geometry::{polygon_centroid, polygon_inertia, Vec2};
+use crate::robot::ArmDescription;
+use crate::timing::Dynamics;
+
+// meters per second squared, downwards
+pub const GRAVITY: f32 = 9.81;
+
+// rigid body carried by the last link, e.g. the grasped object
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Payload {
+    pub mass: f32,
+    // center of mass in the wrist frame
+    pub center: Vec2,
+    // moment of inertia about the center of mass
+    pub inertia: f32,
+}
+
+impl Payload {
+    // uniform polygon with vertices in the wrist frame
+    pub fn from_polygon(vertices: &[Vec2], mass: f32) -> Payload {
+        Payload {
+            mass,
+            center: polygon_centroid(vertices),
+            inertia: polygon_inertia(vertices, mass),
+        }
+    }
+}
+
+// state of a link during the recursion
+#[derive(Copy, Clone)]
+struct LinkMotion {
+    direction: Vec2,
+    angle: f32,
+    omega: f32,
+    alpha: f32,
+    // linear acceleration of the joint, including gravity
+    acceleration: Vec2,
+}
+
+impl LinkMotion {
+    // linear acceleration of a point at `r` from the joint
+    fn point_acceleration(&self, r: Vec2) -> Vec2 {
+        self.acceleration + r.perp() * self.alpha - r * (self.omega * self.omega)
+    }
+}
+
+// rigid body model of the planar chain, angles are in degrees like everywhere else and
+// converted to radians internally
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArmModel {
+    pub description: ArmDescription,
+    pub payload: Option<Payload>,
+    pub gravity: f32,
+}
+
+impl ArmModel {
+    pub fn new(description: ArmDescription) -> ArmModel {
+        ArmModel {
+            description,
+            payload: None,
+            gravity: GRAVITY,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: Option<Payload>) -> ArmModel {
+        self.payload = payload;
+        self
+    }
+
+    // joint torques in newton meters that produce the given joint velocities and
+    // accelerations, in degrees per second (squared), recursive Newton-Euler
+    pub fn inverse_dynamics(
+        &self,
+        angles: &[f32],
+        velocities: &[f32],
+        accelerations: &[f32],
+    ) -> Vec<f32> {
+        let links = &self.description.links;
+
+        // outwards: motion of every link, gravity enters as an upward acceleration of the base
+        let mut motions: Vec<LinkMotion> = Vec::with_capacity(links.len());
+        let mut state = LinkMotion {
+            direction: Vec2::ZERO,
+            angle: 90.0,
+            omega: 0.0,
+            alpha: 0.0,
+            acceleration: Vec2::new(0.0, self.gravity),
+        };
+        for (i, link) in links.iter().enumerate() {
+            state.angle += angles[i];
+            state.omega += velocities[i].to_radians();
+            state.alpha += accelerations[i].to_radians();
+            let (sin, cos) = state.angle.to_radians().sin_cos();
+            state.direction = Vec2::new(cos, sin);
+            let next = state.point_acceleration(state.direction * link.length);
+            motions.push(state);
+            state.acceleration = next;
+        }
+
+        // inwards: forces and moments about each joint
+        let mut torques = vec![0.0; links.len()];
+        let (mut force, mut moment) = (Vec2::ZERO, 0.0);
+        for (i, (link, motion)) in links.iter().zip(motions.iter()).enumerate().rev() {
+            let tip = motion.direction * link.length;
+            moment += tip.cross(force);
+
+            let mut bodies = vec![(
+                link.mass,
+                motion.direction * link.center_of_mass,
+                link.inertia,
+            )];
+            if let (Some(payload), true) = (self.payload, i + 1 == links.len()) {
+                let center = payload.center.rotated_about(Vec2::ZERO, motion.angle);
+                bodies.push((payload.mass, tip + center, payload.inertia));
+            }
+            for (mass, r, inertia) in bodies {
+                let inertial = motion.point_acceleration(r) * mass;
+                force += inertial;
+                moment += inertia * motion.alpha + r.cross(inertial);
+            }
+            torques[i] = moment;
+        }
+        torques
+    }
+
+    // joint space inertia, torque per degree per second squared
+    pub fn mass_matrix(&self, angles: &[f32]) -> Vec<Vec<f32>> {
+        let n = angles.len();
+        let zero = vec![0.0; n];
+        let model = ArmModel {
+            gravity: 0.0,
+            ..self.clone()
+        };
+        let mut matrix = vec![vec![0.0; n]; n];
+        for j in 0..n {
+            let mut unit = zero.clone();
+            unit[j] = 1.0;
+            for (i, torque) in model
+                .inverse_dynamics(angles, &zero, &unit)
+                .into_iter()
+                .enumerate()
+            {
+                matrix[i][j] = torque;
+            }
+        }
+        matrix
+    }
+
+    // joint accelerations in degrees per second squared under the joint `torques`
+    pub fn forward_dynamics(
+        &self,
+        angles: &[f32],
+        velocities: &[f32],
+        torques: &[f32],
+    ) -> Vec<f32> {
+        let bias = self.inverse_dynamics(angles, velocities, &vec![0.0; angles.len()]);
+        let rhs = torques
+            .iter()
+            .zip(bias.iter())
+            .map(|(t, b)| t - b)
+            .collect();
+        solve(self.mass_matrix(angles), rhs)
+    }
+}
+
+impl Dynamics for ArmModel {
+    fn torques(&self, angles: &[f32], velocities: &[f32], accelerations: &[f32]) -> Vec<f32> {
+        self.inverse_dynamics(angles, velocities, accelerations)
+    }
+
+    fn torque_limits(&self) -> Vec<f32> {
+        self.description
+            .links
+            .iter()
+            .map(|link| link.max_torque)
+            .collect()
+    }
+}
+
+// solve `matrix * x = rhs` by gaussian elimination with partial pivoting
+fn solve(mut matrix: Vec<Vec<f32>>, mut rhs: Vec<f32>) -> Vec<f32> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))
+            .unwrap();
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        if matrix[col][col].abs() < 1e-12 {
+            continue;
+        }
+        let (upper, lower) = matrix.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot) in row[col..].iter_mut().zip(pivot_row[col..].iter()) {
+                *value -= factor * pivot;
+            }
+            rhs[col + 1 + offset] -= factor * rhs[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f32 = (row + 1..n).map(|k| matrix[row][k] * x[k]).sum();
+        x[row] = if matrix[row][row].abs() < 1e-12 {
+            0.0
+        } else {
+            (rhs[row] - sum) / matrix[row][row]
+        };
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(model: &ArmModel) {
+        let angles = [-30.0, -60.0, -45.0];
+        let velocities = [40.0, -25.0, 60.0];
+        let accelerations = [-120.0, 200.0, 80.0];
+        let torques = model.inverse_dynamics(&angles, &velocities, &accelerations);
+        let result = model.forward_dynamics(&angles, &velocities, &torques);
+        for (expected, actual) in accelerations.iter().zip(result.iter()) {
+            assert!((expected - actual).abs() < 1e-2, "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn forward_inverts_inverse_dynamics() {
+        round_trip(&ArmModel::new(ArmDescription::default()));
+    }
+
+    #[test]
+    fn forward_inverts_inverse_dynamics_with_payload() {
+        let object = [
+            Vec2::new(0.02, -0.03),
+            Vec2::new(0.1, -0.03),
+            Vec2::new(0.1, 0.05),
+            Vec2::new(0.02, 0.05),
+        ];
+        let payload = Payload::from_polygon(&object, 0.5);
+        let model = ArmModel::new(ArmDescription::default());
+        let loaded = model.clone().with_payload(Some(payload));
+        let angles = [-30.0, -60.0, -45.0];
+        assert_ne!(
+            model.inverse_dynamics(&angles, &[0.0; 3], &[0.0; 3]),
+            loaded.inverse_dynamics(&angles, &[0.0; 3], &[0.0; 3])
+        );
+        round_trip(&loaded);
+    }
+}
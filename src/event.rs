@@ -0,0 +1,54 @@
+use std::fmt;
+
+use crate::geometry::Vec2;
+use crate::motion::MotionError;
+use crate::robot::ArmPart;
+
+// something that happened during a step, the world keeps them until they are taken
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    // the grasped object slipped out of the claws, `position` is its center at that moment
+    GraspLost {
+        object: String,
+        time: f32,
+        position: Vec2,
+    },
+    // a motion was stopped or not started because two parts of the arm would run into each
+    // other
+    SelfCollision {
+        first: ArmPart,
+        second: ArmPart,
+        time: f32,
+    },
+    // a part of the arm ran into its surroundings, the arm holds still until the stop is reset,
+    // `position` is the deepest contact point
+    ProtectiveStop {
+        part: ArmPart,
+        obstacle: Obstacle,
+        position: Vec2,
+        time: f32,
+    },
+    // the next taught step of a running program could not start, the program stopped
+    ProgramAborted {
+        error: MotionError,
+        time: f32,
+    },
+}
+
+// what the arm can run into besides itself
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Obstacle {
+    Ground,
+    Fixture(String),
+    Object(String),
+}
+
+impl fmt::Display for Obstacle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Obstacle::Ground => write!(f, "the ground"),
+            Obstacle::Fixture(name) => write!(f, "fixture {}", name),
+            Obstacle::Object(name) => write!(f, "object {}", name),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+    pub const fn new(x: f32, y: f32) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    pub fn dot(self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    // z component of the 3d cross product
+    pub fn cross(self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    // rotated by 90 degrees counter-clockwise
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    // rotate by `angle` degrees counter-clockwise around `center`
+    pub fn rotated_about(self, center: Vec2, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let d = self - center;
+        Vec2::new(cos * d.x - sin * d.y, sin * d.x + cos * d.y) + center
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, s: f32) -> Vec2 {
+        Vec2::new(self.x * s, self.y * s)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+// signed area of a polygon, positive for counter-clockwise vertices
+pub fn polygon_area(vertices: &[Vec2]) -> f32 {
+    edges(vertices).map(|(a, b)| a.cross(b)).sum::<f32>() / 2.0
+}
+
+pub fn polygon_centroid(vertices: &[Vec2]) -> Vec2 {
+    let area = polygon_area(vertices);
+    if area.abs() < 1e-12 {
+        // degenerate polygon, fall back to the vertex average
+        let sum = vertices.iter().fold(Vec2::ZERO, |sum, v| sum + *v);
+        return sum * (1.0 / vertices.len().max(1) as f32);
+    }
+    let sum = edges(vertices).fold(Vec2::ZERO, |sum, (a, b)| sum + (a + b) * a.cross(b));
+    sum * (1.0 / (6.0 * area))
+}
+
+// moment of inertia of a uniform polygon with `mass` about its centroid
+pub fn polygon_inertia(vertices: &[Vec2], mass: f32) -> f32 {
+    let centroid = polygon_centroid(vertices);
+    let (mut numerator, mut denominator) = (0.0, 0.0);
+    for (a, b) in edges(vertices) {
+        let (a, b) = (a - centroid, b - centroid);
+        let cross = a.cross(b);
+        numerator += cross * (a.dot(a) + a.dot(b) + b.dot(b));
+        denominator += cross;
+    }
+    if denominator.abs() < 1e-12 {
+        return 0.0;
+    }
+    mass * numerator / (6.0 * denominator)
+}
+
+// consecutive vertex pairs including the closing edge
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
@@ -0,0 +1,213 @@
+use crate::kinematics::Frame;
+use crate::robot::ArmDescription;
+use std::error::Error;
+use std::fmt;
+
+// tolerance in degrees when comparing against joint limits, the elbow angle is
+// ill-conditioned close to the stretched arm
+const LIMIT_EPSILON: f32 = 0.05;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IkError {
+    // the analytic solution only exists for arms with three links
+    UnsupportedTopology { links: usize },
+    // the top joint would have to be outside of the annulus the first two links can reach
+    OutOfReach { distance: f32, min: f32, max: f32 },
+    // the top joint coincides with the base joint, the base angle is undetermined
+    Singular,
+    // the pose is reachable geometrically, but not within the joint ranges
+    JointLimits,
+}
+
+impl fmt::Display for IkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IkError::UnsupportedTopology { links } => write!(
+                f,
+                "inverse kinematics needs an arm with 3 links, this one has {}",
+                links
+            ),
+            IkError::OutOfReach { distance, min, max } => write!(
+                f,
+                "target out of reach: top joint distance {} outside of [{}, {}]",
+                distance, min, max
+            ),
+            IkError::Singular => write!(f, "target is singular: top joint coincides with the base"),
+            IkError::JointLimits => write!(f, "target cannot be reached within the joint ranges"),
+        }
+    }
+}
+
+impl Error for IkError {}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Elbow {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IkSolution {
+    pub angles: Vec<f32>,
+    pub elbow: Elbow,
+}
+
+// wrap an angle in degrees into (-180, 180]
+pub fn normalize_angle(angle: f32) -> f32 {
+    let wrapped = (angle + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 {
+        180.0
+    } else {
+        wrapped
+    }
+}
+
+// all representations of `angle` (modulo 360) inside the given range
+fn candidates(angle: f32, (min, max): (f32, f32)) -> Vec<f32> {
+    [-360.0, 0.0, 360.0]
+        .iter()
+        .map(|turn| angle + turn)
+        .filter(|a| *a >= min - LIMIT_EPSILON && *a <= max + LIMIT_EPSILON)
+        .map(|a| a.clamp(min, max))
+        .collect()
+}
+
+// all joint configurations of a three-link arm placing the tool center point at
+// `target`, the target angle is the absolute tool orientation in degrees
+pub fn inverse_kinematics(arm: &ArmDescription, target: Frame) -> Result<Vec<IkSolution>, IkError> {
+    let [l1, l2, l3] = match arm.links.as_slice() {
+        [l1, l2, l3] => [l1, l2, l3],
+        links => return Err(IkError::UnsupportedTopology { links: links.len() }),
+    };
+    let (a, b) = (l1.length, l2.length);
+
+    // position of the top joint, the last link is fixed by the tool orientation
+    let top = target.origin - target.direction() * (arm.claw.length + l3.length);
+    let d = top - arm.base;
+    let distance = d.length();
+    let (min, max) = ((a - b).abs(), a + b);
+
+    if distance > max + 1e-5 || distance < min - 1e-5 {
+        return Err(IkError::OutOfReach { distance, min, max });
+    }
+    if distance < 1e-5 {
+        return Err(IkError::Singular);
+    }
+
+    // law of cosines for the elbow
+    let cos_elbow = ((distance * distance - a * a - b * b) / (2.0 * a * b)).clamp(-1.0, 1.0);
+    let elbow_angle = cos_elbow.acos();
+
+    let mut solutions = Vec::new();
+    for (elbow, q2) in [(Elbow::Up, -elbow_angle), (Elbow::Down, elbow_angle)] {
+        let link1 = d.y.atan2(d.x) - (b * q2.sin()).atan2(a + b * q2.cos());
+        // the base link points up at zero
+        let q1 = normalize_angle(link1.to_degrees() - 90.0);
+        let q2 = normalize_angle(q2.to_degrees());
+        let q3 = normalize_angle(target.angle - 90.0 - q1 - q2);
+
+        for a1 in candidates(q1, l1.limits) {
+            for a2 in candidates(q2, l2.limits) {
+                for a3 in candidates(q3, l3.limits) {
+                    let solution = IkSolution {
+                        angles: vec![a1, a2, a3],
+                        elbow,
+                    };
+                    if !solutions.contains(&solution) {
+                        solutions.push(solution);
+                    }
+                }
+            }
+        }
+
+        // stretched arm, both elbow configurations are the same
+        if elbow_angle.abs() < 1e-6 {
+            break;
+        }
+    }
+
+    if solutions.is_empty() {
+        return Err(IkError::JointLimits);
+    }
+    Ok(solutions)
+}
+
+// solution with the smallest joint motion from `current`
+pub fn closest_solution(solutions: &[IkSolution], current: &[f32]) -> Option<IkSolution> {
+    let cost = |s: &IkSolution| -> f32 {
+        s.angles
+            .iter()
+            .zip(current.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum()
+    };
+    solutions
+        .iter()
+        .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Vec2;
+    use crate::kinematics::tcp_pose;
+
+    // open joint ranges, so both elbow configurations are within the limits
+    fn unlimited_arm() -> ArmDescription {
+        let mut arm = ArmDescription::default();
+        for link in arm.links.iter_mut() {
+            link.limits = (-180.0, 180.0);
+        }
+        arm
+    }
+
+    #[test]
+    fn round_trip() {
+        let arm = unlimited_arm();
+        let target = Frame {
+            origin: Vec2::new(-0.1, -0.3),
+            angle: -60.0,
+        };
+        let solutions = inverse_kinematics(&arm, target).unwrap();
+        for elbow in [Elbow::Up, Elbow::Down] {
+            let solution = solutions.iter().find(|s| s.elbow == elbow).unwrap();
+            let pose = tcp_pose(&arm, &solution.angles);
+            assert!(
+                (pose.origin - target.origin).length() < 1e-4,
+                "{:?}",
+                solution
+            );
+            assert!(
+                normalize_angle(pose.angle - target.angle).abs() < 1e-2,
+                "{:?}",
+                solution
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_reach() {
+        let arm = ArmDescription::default();
+        let target = Frame {
+            origin: Vec2::new(1.0, 0.5),
+            angle: 0.0,
+        };
+        assert!(matches!(
+            inverse_kinematics(&arm, target),
+            Err(IkError::OutOfReach { distance, max, .. }) if distance > max
+        ));
+    }
+
+    #[test]
+    fn singular() {
+        let arm = unlimited_arm();
+        // the last link and the claws end at the target with the top joint on the base
+        let direction = Vec2::new(0.0, -1.0);
+        let target = Frame {
+            origin: arm.base + direction * (arm.claw.length + arm.links[2].length),
+            angle: -90.0,
+        };
+        assert_eq!(inverse_kinematics(&arm, target), Err(IkError::Singular));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::geometry::Vec2;
+use crate::robot::ArmDescription;
+
+pub const BASE: Vec2 = Vec2::new(-0.5, -0.4);
+
+// joint angles in degrees, each relative to the previous link,
+// zero means the link is straight in line with the previous one (or points up for the base)
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointState {
+    pub angles: Vec<f32>,
+    // opening angle of the claws, 0 is closed
+    pub claw: f32,
+}
+
+// coordinate frame in the plane, `angle` in degrees
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub origin: Vec2,
+    pub angle: f32,
+}
+
+impl Frame {
+    pub fn transform(&self, point: Vec2) -> Vec2 {
+        (self.origin + point).rotated_about(self.origin, self.angle)
+    }
+
+    pub fn inverse_transform(&self, point: Vec2) -> Vec2 {
+        point.rotated_about(self.origin, -self.angle) - self.origin
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        Vec2::new(cos, sin)
+    }
+}
+
+// frames of all links, origin at the joint and x axis pointing along the link
+pub fn forward_kinematics(arm: &ArmDescription, angles: &[f32]) -> Vec<Frame> {
+    let mut frame = Frame {
+        origin: arm.base,
+        angle: 90.0,
+    };
+    let mut frames = Vec::with_capacity(arm.links.len());
+    for (link, angle) in arm.links.iter().zip(angles.iter()) {
+        frame.angle += angle;
+        frames.push(frame);
+        frame.origin += frame.direction() * link.length;
+    }
+    frames
+}
+
+// velocity of `point` when it moves along with the last link, joint velocities in degrees per
+// second
+pub fn point_velocity(
+    arm: &ArmDescription,
+    angles: &[f32],
+    velocities: &[f32],
+    point: Vec2,
+) -> Vec2 {
+    forward_kinematics(arm, angles)
+        .iter()
+        .zip(velocities.iter())
+        .fold(Vec2::ZERO, |sum, (frame, velocity)| {
+            sum + (point - frame.origin).perp() * velocity.to_radians()
+        })
+}
+
+// frame at the tip of the last chain, the claws are attached here
+pub fn wrist(arm: &ArmDescription, angles: &[f32]) -> Frame {
+    match (forward_kinematics(arm, angles).last(), arm.links.last()) {
+        (Some(frame), Some(link)) => Frame {
+            origin: frame.origin + frame.direction() * link.length,
+            angle: frame.angle,
+        },
+        _ => Frame {
+            origin: arm.base,
+            angle: 90.0,
+        },
+    }
+}
+
+// the tool center point lies between the claw tips
+pub fn tcp_pose(arm: &ArmDescription, angles: &[f32]) -> Frame {
+    let wrist = wrist(arm, angles);
+    Frame {
+        origin: wrist.origin + wrist.direction() * arm.claw.length,
+        angle: wrist.angle,
+    }
+}
@@ -0,0 +1,22 @@
+pub mod clock;
+pub mod collision;
+pub mod config;
+pub mod dynamics;
+pub mod event;
+pub mod geometry;
+pub mod ik;
+pub mod kinematics;
+pub mod motion;
+pub mod physics;
+pub mod profile;
+#[cfg(feature = "rapier")]
+pub mod rapier;
+pub mod robot;
+pub mod servo;
+pub mod timing;
+pub mod world;
+
+// the code examples of the readme are compiled and run as doctests
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct Readme;
@@ -0,0 +1,831 @@
+use crate::geometry::Vec2;
+use crate::ik::{inverse_kinematics, normalize_angle, Elbow, IkError};
+use crate::kinematics::{tcp_pose, Frame};
+use crate::profile::{KinematicLimits, Profile, ProfileKind};
+use crate::robot::{ArmDescription, ArmPart};
+use std::error::Error;
+use std::fmt;
+
+// default speed of the tool center point in units per second
+pub const DEF_TCP_SPEED: f32 = 0.2;
+// rotation speed of the tool in degrees per second
+pub const TCP_ROTATION_SPEED: f32 = 90.0;
+
+// distance between two inverse kinematics points of a cartesian path
+const PATH_RESOLUTION: f32 = 0.005;
+const ANGLE_RESOLUTION: f32 = 1.0;
+// larger joint changes between two path points mean the arm flips through a singularity
+const MAX_JOINT_STEP: f32 = 15.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MotionError {
+    // no joint configuration for the pose at `fraction` of the path
+    Unreachable { fraction: f32, source: IkError },
+    // the path crosses a singularity at `fraction` of the path
+    Singular { fraction: f32 },
+    // the joints cannot follow the path within their limits at `fraction` of the path
+    Infeasible { fraction: f32 },
+    // start, via and end point of a circular move do not define an arc
+    Collinear,
+    // two parts of the arm would run into each other along the path
+    SelfCollision { first: ArmPart, second: ArmPart },
+    // the arm stands in a protective stop until it is reset
+    ProtectiveStop,
+}
+
+impl fmt::Display for MotionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MotionError::Unreachable { fraction, source } => write!(
+                f,
+                "path leaves the workspace at {:.0}%: {}",
+                fraction * 100.0,
+                source
+            ),
+            MotionError::Singular { fraction } => {
+                write!(f, "path crosses a singularity at {:.0}%", fraction * 100.0)
+            }
+            MotionError::Infeasible { fraction } => write!(
+                f,
+                "path cannot be followed within the joint limits at {:.0}%",
+                fraction * 100.0
+            ),
+            MotionError::Collinear => write!(f, "start, via and end point lie on a line"),
+            MotionError::SelfCollision { first, second } => {
+                write!(f, "{} would hit {}", first, second)
+            }
+            MotionError::ProtectiveStop => write!(f, "the arm is in a protective stop"),
+        }
+    }
+}
+
+impl Error for MotionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MotionError::Unreachable { source, .. } => Some(source),
+            MotionError::Singular { .. }
+            | MotionError::Infeasible { .. }
+            | MotionError::Collinear
+            | MotionError::SelfCollision { .. }
+            | MotionError::ProtectiveStop => None,
+        }
+    }
+}
+
+// a planned motion of all joints, sampled by time since its start
+pub trait Motion {
+    // seconds
+    fn duration(&self) -> f32;
+    // joint angles in degrees at `t` seconds, clamped to the duration
+    fn sample(&self, t: f32) -> Vec<f32>;
+
+    // joint velocities in degrees per second, numerically differentiated unless overridden
+    fn velocity(&self, t: f32) -> Vec<f32> {
+        let h = DIFFERENCE_STEP;
+        difference(&self.sample(t - h), &self.sample(t + h), 2.0 * h)
+    }
+
+    // joint accelerations in degrees per second squared
+    fn acceleration(&self, t: f32) -> Vec<f32> {
+        let h = DIFFERENCE_STEP;
+        difference(&self.velocity(t - h), &self.velocity(t + h), 2.0 * h)
+    }
+
+    // geometric path of the motion as joint angles, sampled evenly in time unless overridden
+    fn path(&self) -> Vec<Vec<f32>> {
+        (0..=PATH_POINTS)
+            .map(|i| self.sample(self.duration() * i as f32 / PATH_POINTS as f32))
+            .collect()
+    }
+}
+
+// time step of the numeric derivatives
+const DIFFERENCE_STEP: f32 = 0.005;
+// number of segments of a sampled path
+const PATH_POINTS: usize = 200;
+
+fn difference(before: &[f32], after: &[f32], dt: f32) -> Vec<f32> {
+    before
+        .iter()
+        .zip(after.iter())
+        .map(|(a, b)| (b - a) / dt)
+        .collect()
+}
+
+// planned state of all joints at one point in time
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionSample {
+    pub time: f32,
+    pub position: Vec<f32>,
+    pub velocity: Vec<f32>,
+    pub acceleration: Vec<f32>,
+}
+
+// sample a motion every `dt` seconds including its end, e.g. to plot the profile
+pub fn tabulate(motion: &dyn Motion, dt: f32) -> Vec<MotionSample> {
+    let steps = (motion.duration() / dt).ceil() as usize;
+    (0..=steps)
+        .map(|i| {
+            let time = (i as f32 * dt).min(motion.duration());
+            MotionSample {
+                time,
+                position: motion.sample(time),
+                velocity: motion.velocity(time),
+                acceleration: motion.acceleration(time),
+            }
+        })
+        .collect()
+}
+
+// tool angle during a circular move
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Orientation {
+    // the tool keeps the angle it has at the start
+    #[default]
+    Fixed,
+    // the tool turns with the arc, keeping its angle to the tangent
+    Tangent,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum MotionMode {
+    // joints move one after another, starting at the base
+    Sequential,
+    // all joints start and finish together (MoveJ)
+    #[default]
+    Synchronized,
+}
+
+// joint space interpolated move, all joints follow the same velocity profile scaled to their
+// distance, so the slowest joint determines the duration
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointMove {
+    start: Vec<f32>,
+    end: Vec<f32>,
+    profile: Profile,
+}
+
+impl JointMove {
+    pub fn new(
+        start: &[f32],
+        end: &[f32],
+        limits: &[KinematicLimits],
+        kind: ProfileKind,
+    ) -> JointMove {
+        // limits of the normalized move from 0 to 1, every joint stays within its own
+        let mut normalized = KinematicLimits {
+            velocity: f32::INFINITY,
+            acceleration: f32::INFINITY,
+            jerk: f32::INFINITY,
+        };
+        for ((a, b), limits) in start.iter().zip(end.iter()).zip(limits.iter()) {
+            let distance = (b - a).abs();
+            if distance > 0.0 {
+                normalized.velocity = normalized.velocity.min(limits.velocity / distance);
+                normalized.acceleration =
+                    normalized.acceleration.min(limits.acceleration / distance);
+                normalized.jerk = normalized.jerk.min(limits.jerk / distance);
+            }
+        }
+        let distance = if normalized.velocity.is_finite() {
+            1.0
+        } else {
+            0.0
+        };
+
+        JointMove {
+            start: start.to_vec(),
+            end: end.to_vec(),
+            profile: Profile::new(kind, distance, normalized),
+        }
+    }
+
+    // joint angles at the normalized position `s`
+    fn position(&self, s: f32) -> Vec<f32> {
+        self.start
+            .iter()
+            .zip(self.end.iter())
+            .map(|(a, b)| a + (b - a) * s)
+            .collect()
+    }
+
+    // joint rates for the normalized rate `ds`
+    fn rate(&self, ds: f32) -> Vec<f32> {
+        self.start
+            .iter()
+            .zip(self.end.iter())
+            .map(|(a, b)| (b - a) * ds)
+            .collect()
+    }
+}
+
+impl Motion for JointMove {
+    fn duration(&self) -> f32 {
+        self.profile.duration()
+    }
+
+    fn sample(&self, t: f32) -> Vec<f32> {
+        if t >= self.duration() {
+            return self.end.clone();
+        }
+        self.position(self.profile.sample(t).position)
+    }
+
+    fn velocity(&self, t: f32) -> Vec<f32> {
+        self.rate(self.profile.sample(t).velocity)
+    }
+
+    fn acceleration(&self, t: f32) -> Vec<f32> {
+        self.rate(self.profile.sample(t).acceleration)
+    }
+
+    fn path(&self) -> Vec<Vec<f32>> {
+        (0..=PATH_POINTS)
+            .map(|i| self.position(i as f32 / PATH_POINTS as f32))
+            .collect()
+    }
+}
+
+// motions executed one after another
+pub struct Sequence {
+    motions: Vec<Box<dyn Motion>>,
+}
+
+impl Sequence {
+    pub fn new(motions: Vec<Box<dyn Motion>>) -> Sequence {
+        Sequence { motions }
+    }
+
+    // motion running at `t` and the time since its start
+    fn locate(&self, mut t: f32) -> Option<(&dyn Motion, f32)> {
+        for motion in self.motions.iter() {
+            if t <= motion.duration() {
+                return Some((motion.as_ref(), t));
+            }
+            t -= motion.duration();
+        }
+        self.motions
+            .last()
+            .map(|motion| (motion.as_ref(), motion.duration()))
+    }
+}
+
+impl Motion for Sequence {
+    fn duration(&self) -> f32 {
+        self.motions.iter().map(|m| m.duration()).sum()
+    }
+
+    fn sample(&self, t: f32) -> Vec<f32> {
+        self.locate(t)
+            .map(|(motion, t)| motion.sample(t))
+            .unwrap_or_default()
+    }
+
+    fn velocity(&self, t: f32) -> Vec<f32> {
+        self.locate(t)
+            .map(|(motion, t)| motion.velocity(t))
+            .unwrap_or_default()
+    }
+
+    fn acceleration(&self, t: f32) -> Vec<f32> {
+        self.locate(t)
+            .map(|(motion, t)| motion.acceleration(t))
+            .unwrap_or_default()
+    }
+
+    fn path(&self) -> Vec<Vec<f32>> {
+        let mut points: Vec<Vec<f32>> = Vec::new();
+        for motion in self.motions.iter() {
+            for point in motion.path() {
+                if points.last() != Some(&point) {
+                    points.push(point);
+                }
+            }
+        }
+        points
+    }
+}
+
+// two overlapping motions, the second one starts `offset` seconds into the first and its
+// displacement is added on top, so the arm leaves the first motion before it comes to rest
+pub struct Blend {
+    first: Box<dyn Motion>,
+    second: Box<dyn Motion>,
+    offset: f32,
+}
+
+impl Blend {
+    pub fn new(first: Box<dyn Motion>, second: Box<dyn Motion>, offset: f32) -> Blend {
+        Blend {
+            first,
+            second,
+            offset,
+        }
+    }
+
+    // the motion that was blended into the next one
+    pub fn into_first(self) -> Box<dyn Motion> {
+        self.first
+    }
+}
+
+impl Motion for Blend {
+    fn duration(&self) -> f32 {
+        self.first
+            .duration()
+            .max(self.offset + self.second.duration())
+    }
+
+    fn sample(&self, t: f32) -> Vec<f32> {
+        let first = self.first.sample(t);
+        if t < self.offset {
+            return first;
+        }
+        let (start, second) = (self.second.sample(0.0), self.second.sample(t - self.offset));
+        first
+            .iter()
+            .zip(start.iter().zip(second.iter()))
+            .map(|(a, (s, b))| a + b - s)
+            .collect()
+    }
+
+    fn velocity(&self, t: f32) -> Vec<f32> {
+        let first = self.first.velocity(t);
+        if t < self.offset {
+            return first;
+        }
+        let second = self.second.velocity(t - self.offset);
+        first
+            .iter()
+            .zip(second.iter())
+            .map(|(a, b)| a + b)
+            .collect()
+    }
+
+    fn acceleration(&self, t: f32) -> Vec<f32> {
+        let first = self.first.acceleration(t);
+        if t < self.offset {
+            return first;
+        }
+        let second = self.second.acceleration(t - self.offset);
+        first
+            .iter()
+            .zip(second.iter())
+            .map(|(a, b)| a + b)
+            .collect()
+    }
+}
+
+// joint move from `start` to `end` in the given mode
+pub fn plan_joint_move(
+    mode: MotionMode,
+    kind: ProfileKind,
+    start: &[f32],
+    end: &[f32],
+    limits: &[KinematicLimits],
+) -> Box<dyn Motion> {
+    match mode {
+        MotionMode::Synchronized => Box::new(JointMove::new(start, end, limits, kind)),
+        MotionMode::Sequential => {
+            let mut current = start.to_vec();
+            let mut moves: Vec<Box<dyn Motion>> = Vec::new();
+            for (i, angle) in end.iter().enumerate() {
+                let mut next = current.clone();
+                next[i] = *angle;
+                moves.push(Box::new(JointMove::new(&current, &next, limits, kind)));
+                current = next;
+            }
+            Box::new(Sequence::new(moves))
+        }
+    }
+}
+
+// move along a cartesian path, joint angles are solved at evenly spaced path points, the
+// path is covered with a trapezoidal profile from 0 to 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct CartesianMove {
+    points: Vec<Vec<f32>>,
+    profile: Profile,
+}
+
+impl Motion for CartesianMove {
+    fn duration(&self) -> f32 {
+        self.profile.duration()
+    }
+
+    fn sample(&self, t: f32) -> Vec<f32> {
+        let segments = self.points.len() - 1;
+        if t >= self.duration() || segments == 0 {
+            return self.points[segments].clone();
+        }
+        let s = self.profile.sample(t).position.clamp(0.0, 1.0) * segments as f32;
+        let i = (s.floor() as usize).min(segments - 1);
+        let f = s - i as f32;
+        self.points[i]
+            .iter()
+            .zip(self.points[i + 1].iter())
+            .map(|(a, b)| a + (b - a) * f)
+            .collect()
+    }
+
+    fn path(&self) -> Vec<Vec<f32>> {
+        self.points.clone()
+    }
+}
+
+fn elbow_of(angles: &[f32]) -> Elbow {
+    match angles.get(1) {
+        Some(angle) if *angle > 0.0 => Elbow::Down,
+        _ => Elbow::Up,
+    }
+}
+
+// plan a move along `path`, which maps the path parameter in [0, 1] to a tool pose,
+// `length` and `rotation` are the distance and the angle the tool covers in total
+pub fn plan_path(
+    arm: &ArmDescription,
+    start: &[f32],
+    path: impl Fn(f32) -> Frame,
+    length: f32,
+    rotation: f32,
+    speed: f32,
+) -> Result<CartesianMove, MotionError> {
+    check_speed(speed)?;
+    let steps = (length / PATH_RESOLUTION)
+        .max(rotation.abs() / ANGLE_RESOLUTION)
+        .ceil()
+        .max(1.0) as usize;
+    let elbow = elbow_of(start);
+
+    let mut points = vec![start.to_vec()];
+    for i in 1..=steps {
+        let fraction = i as f32 / steps as f32;
+        let previous = points.last().unwrap();
+        let solutions = inverse_kinematics(arm, path(fraction)).map_err(|source| match source {
+            IkError::Singular => MotionError::Singular { fraction },
+            source => MotionError::Unreachable { fraction, source },
+        })?;
+        // stay in the elbow configuration of the start, changing it means passing the stretched arm
+        let solution = solutions
+            .into_iter()
+            .filter(|solution| solution.elbow == elbow)
+            .map(|solution| solution.angles)
+            .min_by(|a, b| joint_distance(a, previous).total_cmp(&joint_distance(b, previous)))
+            .ok_or(MotionError::Singular { fraction })?;
+        if joint_distance(&solution, previous) > MAX_JOINT_STEP {
+            return Err(MotionError::Singular { fraction });
+        }
+        points.push(solution);
+    }
+
+    // constant tool speed, slowed down as a whole if a joint would exceed its velocity, the
+    // ramps at the start and the end keep every joint within its acceleration
+    let mut limits = KinematicLimits {
+        velocity: speed / length.max(f32::EPSILON),
+        acceleration: f32::INFINITY,
+        jerk: f32::INFINITY,
+    };
+    if rotation != 0.0 {
+        limits.velocity = limits.velocity.min(TCP_ROTATION_SPEED / rotation.abs());
+    }
+    for pair in points.windows(2) {
+        for ((a, b), link) in pair[0].iter().zip(pair[1].iter()).zip(arm.links.iter()) {
+            // joint distance per unit of the path parameter
+            let rate = (b - a).abs() * steps as f32;
+            if rate > 0.0 {
+                limits.velocity = limits.velocity.min(link.max_velocity / rate);
+                limits.acceleration = limits.acceleration.min(link.max_acceleration / rate);
+            }
+        }
+    }
+    let distance = if length > 0.0 || rotation != 0.0 {
+        1.0
+    } else {
+        0.0
+    };
+
+    Ok(CartesianMove {
+        points,
+        profile: Profile::new(ProfileKind::Trapezoidal, distance, limits),
+    })
+}
+
+// a tool speed that is not positive would never reach the end of the path
+fn check_speed(speed: f32) -> Result<(), MotionError> {
+    if speed > 0.0 && speed.is_finite() {
+        Ok(())
+    } else {
+        Err(MotionError::Infeasible { fraction: 0.0 })
+    }
+}
+
+// largest single joint change between two configurations
+fn joint_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f32::max)
+}
+
+// straight line move of the tool center point to `target` (MoveL), the tool angle
+// turns the short way round
+pub fn plan_linear_move(
+    arm: &ArmDescription,
+    start: &[f32],
+    target: Frame,
+    speed: f32,
+) -> Result<CartesianMove, MotionError> {
+    let from = tcp_pose(arm, start);
+    let delta = target.origin - from.origin;
+    let rotation = normalize_angle(target.angle - from.angle);
+    let path = |s: f32| Frame {
+        origin: from.origin + delta * s,
+        angle: from.angle + rotation * s,
+    };
+    plan_path(arm, start, path, delta.length(), rotation, speed)
+}
+
+// circular move of the tool center point through `via` to `end` (MoveC) with constant speed
+// along the arc
+pub fn plan_circular_move(
+    arm: &ArmDescription,
+    start: &[f32],
+    via: Vec2,
+    end: Vec2,
+    orientation: Orientation,
+    speed: f32,
+) -> Result<CartesianMove, MotionError> {
+    check_speed(speed)?;
+    let from = tcp_pose(arm, start);
+    let (a, b, c) = (from.origin, via, end);
+
+    // center of the circle through the three points
+    let d = 2.0 * ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x));
+    if d.abs() < 1e-6 {
+        return Err(MotionError::Collinear);
+    }
+    let (ab, ac) = (b - a, c - a);
+    let center = a + Vec2::new(
+        (ac.y * ab.dot(ab) - ab.y * ac.dot(ac)) / d,
+        (ab.x * ac.dot(ac) - ac.x * ab.dot(ab)) / d,
+    );
+    let radius = (a - center).length();
+
+    // swept angle from start to end, counter-clockwise if the via point lies on that side
+    let angle_of = |p: Vec2| (p.y - center.y).atan2(p.x - center.x).to_degrees();
+    let begin = angle_of(a);
+    let sweep = (angle_of(c) - begin).rem_euclid(360.0);
+    let sweep = if (angle_of(b) - begin).rem_euclid(360.0) < sweep {
+        sweep
+    } else {
+        sweep - 360.0
+    };
+
+    let rotation = match orientation {
+        Orientation::Fixed => 0.0,
+        Orientation::Tangent => sweep,
+    };
+    let path = |s: f32| Frame {
+        origin: center + Vec2::new(radius, 0.0).rotated_about(Vec2::ZERO, begin + sweep * s),
+        angle: from.angle + rotation * s,
+    };
+    let length = radius * sweep.abs().to_radians();
+    plan_path(arm, start, path, length, rotation, speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_speed() {
+        let arm = ArmDescription::default();
+        let start = [-30.0, -60.0, -60.0];
+        let from = tcp_pose(&arm, &start);
+        let target = Frame {
+            origin: from.origin + Vec2::new(-0.05, 0.0),
+            ..from
+        };
+        let via = from.origin + Vec2::new(-0.025, 0.01);
+        for speed in [0.0, -0.1, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                plan_linear_move(&arm, &start, target, speed).err(),
+                Some(MotionError::Infeasible { fraction: 0.0 })
+            );
+            assert_eq!(
+                plan_circular_move(&arm, &start, via, target.origin, Orientation::Fixed, speed)
+                    .err(),
+                Some(MotionError::Infeasible { fraction: 0.0 })
+            );
+        }
+        assert_eq!(
+            plan_linear_move(&arm, &start, target, DEF_TCP_SPEED).err(),
+            None
+        );
+    }
+
+    #[test]
+    fn tabulates_the_whole_motion() {
+        let arm = ArmDescription::default();
+        let limits = arm.kinematic_limits();
+        let (start, end) = ([0.0, 0.0, -90.0], [-30.0, -60.0, -30.0]);
+        for kind in [ProfileKind::Trapezoidal, ProfileKind::SCurve] {
+            let motion = JointMove::new(&start, &end, &limits, kind);
+            let table = tabulate(&motion, 0.01);
+            let (first, last) = (&table[0], &table[table.len() - 1]);
+            assert_eq!((first.time, &first.position[..]), (0.0, &start[..]));
+            assert_eq!(
+                (last.time, &last.position[..]),
+                (motion.duration(), &end[..])
+            );
+            assert!(first
+                .velocity
+                .iter()
+                .chain(&last.velocity)
+                .all(|v| *v == 0.0));
+            assert!(table
+                .windows(2)
+                .all(|pair| pair[1].time - pair[0].time <= 0.01 + 1e-6));
+            for sample in table.iter() {
+                for ((v, a), limits) in sample
+                    .velocity
+                    .iter()
+                    .zip(sample.acceleration.iter())
+                    .zip(limits.iter())
+                {
+                    assert!(v.abs() <= limits.velocity * 1.001);
+                    assert!(a.abs() <= limits.acceleration * 1.001);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cartesian_moves_ramp_up_and_down() {
+        let arm = ArmDescription::default();
+        let start = [-30.0, -60.0, -60.0];
+        let from = tcp_pose(&arm, &start);
+        let target = Frame {
+            origin: from.origin + Vec2::new(-0.1, 0.0),
+            ..from
+        };
+        let motion = plan_linear_move(&arm, &start, target, DEF_TCP_SPEED).unwrap();
+        let dt = 0.01;
+        let table = tabulate(&motion, dt);
+        let speed = |i: usize| {
+            let (a, b) = (&table[i].position, &table[i + 1].position);
+            (tcp_pose(&arm, b).origin - tcp_pose(&arm, a).origin).length() / dt
+        };
+        // starts and ends at rest, cruises at the tool speed in between
+        assert!(speed(0) < 0.2 * DEF_TCP_SPEED);
+        assert!(speed(table.len() - 2) < 0.2 * DEF_TCP_SPEED);
+        assert!((speed(table.len() / 2) - DEF_TCP_SPEED).abs() < 0.01 * DEF_TCP_SPEED);
+
+        let velocities: Vec<Vec<f32>> = table
+            .windows(2)
+            .map(|pair| difference(&pair[0].position, &pair[1].position, dt))
+            .collect();
+        for pair in velocities.windows(2) {
+            for ((a, b), link) in pair[0].iter().zip(pair[1].iter()).zip(arm.links.iter()) {
+                assert!((b - a).abs() / dt <= link.max_acceleration * 1.05);
+            }
+        }
+    }
+
+    // arc from the start through a point `height` above to one to the left of it, with its
+    // center
+    fn arc(orientation: Orientation, height: f32) -> (ArmDescription, CartesianMove, Vec2, Vec2) {
+        let arm = ArmDescription::default();
+        let start = [-30.0, -60.0, -60.0];
+        let from = tcp_pose(&arm, &start).origin;
+        let via = from + Vec2::new(-0.05, height);
+        let end = from + Vec2::new(-0.1, 0.0);
+        let motion =
+            plan_circular_move(&arm, &start, via, end, orientation, DEF_TCP_SPEED).unwrap();
+        // on the perpendicular bisector of start and end, as far from the via point as from both
+        let middle = from + Vec2::new(-0.05, 0.0);
+        let center = middle - Vec2::new(0.0, (0.05 * 0.05 - height * height) / (2.0 * height));
+        (arm, motion, via, center)
+    }
+
+    #[test]
+    fn circular_moves_pass_the_via_point() {
+        let (arm, motion, via, center) = arc(Orientation::Fixed, 0.02);
+        let origins: Vec<Vec2> = motion
+            .path()
+            .iter()
+            .map(|angles| tcp_pose(&arm, angles).origin)
+            .collect();
+        let radius = (via - center).length();
+        assert!(origins
+            .iter()
+            .all(|origin| ((*origin - center).length() - radius).abs() < 1e-3));
+        let closest = origins
+            .iter()
+            .map(|origin| (*origin - via).length())
+            .fold(f32::INFINITY, f32::min);
+        assert!(closest < PATH_RESOLUTION);
+    }
+
+    #[test]
+    fn circular_moves_keep_the_tool_speed() {
+        let (arm, motion, _, _) = arc(Orientation::Fixed, 0.02);
+        let dt = 0.01;
+        let table = tabulate(&motion, dt);
+        let speeds: Vec<f32> = table
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (&pair[0].position, &pair[1].position);
+                (tcp_pose(&arm, b).origin - tcp_pose(&arm, a).origin).length() / dt
+            })
+            .collect();
+        // the ramps are at the ends, in between the tool cruises along the arc
+        let cruise = &speeds[speeds.len() / 3..speeds.len() * 2 / 3];
+        assert!(cruise
+            .iter()
+            .all(|speed| (speed - DEF_TCP_SPEED).abs() < 0.02 * DEF_TCP_SPEED));
+    }
+
+    #[test]
+    fn tangent_orientation_follows_the_arc() {
+        let (arm, motion, _, center) = arc(Orientation::Tangent, 0.004);
+        let path = motion.path();
+        let start = tcp_pose(&arm, &path[0]);
+        // the tool keeps its angle to the radius, and so to the tangent
+        let to_radius = |frame: Frame| {
+            let radial = frame.origin - center;
+            normalize_angle(frame.angle - radial.y.atan2(radial.x).to_degrees())
+        };
+        for angles in path.iter() {
+            let turn = normalize_angle(to_radius(tcp_pose(&arm, angles)) - to_radius(start));
+            assert!(turn.abs() < 0.5, "{turn}");
+        }
+        let end = tcp_pose(&arm, path.last().unwrap());
+        assert!(normalize_angle(end.angle - start.angle).abs() > 10.0);
+    }
+
+    #[test]
+    fn rejects_collinear_circle_points() {
+        let arm = ArmDescription::default();
+        let start = [-30.0, -60.0, -60.0];
+        let from = tcp_pose(&arm, &start).origin;
+        let end = from + Vec2::new(-0.1, 0.0);
+        for via in [from + Vec2::new(-0.05, 0.0), from, end] {
+            assert_eq!(
+                plan_circular_move(&arm, &start, via, end, Orientation::Fixed, DEF_TCP_SPEED).err(),
+                Some(MotionError::Collinear)
+            );
+        }
+    }
+
+    #[test]
+    fn joint_moves_follow_the_motion_mode() {
+        let arm = ArmDescription::default();
+        let limits = arm.kinematic_limits();
+        let (start, end) = ([0.0, 0.0, -90.0], [-60.0, -20.0, -30.0]);
+        let dt = 0.01;
+
+        // synchronized joints are all on their way until they arrive together
+        let motion = plan_joint_move(
+            MotionMode::Synchronized,
+            ProfileKind::Trapezoidal,
+            &start,
+            &end,
+            &limits,
+        );
+        let table = tabulate(motion.as_ref(), dt);
+        for sample in &table[1..table.len() - 1] {
+            assert!(sample.velocity.iter().all(|v| v.abs() > 0.0));
+        }
+        let arrival = |joint: usize| {
+            table
+                .iter()
+                .find(|sample| (sample.position[joint] - end[joint]).abs() < 1e-3)
+                .unwrap()
+                .time
+        };
+        assert!((0..3).all(|joint| (arrival(joint) - motion.duration()).abs() <= dt));
+
+        // sequential joints move one at a time, starting at the base
+        let motion = plan_joint_move(
+            MotionMode::Sequential,
+            ProfileKind::Trapezoidal,
+            &start,
+            &end,
+            &limits,
+        );
+        let table = tabulate(motion.as_ref(), dt);
+        let mut moving = Vec::new();
+        for sample in table.iter() {
+            let joints: Vec<usize> = (0..3).filter(|i| sample.velocity[*i] != 0.0).collect();
+            assert!(joints.len() <= 1, "{joints:?} at {}", sample.time);
+            if let Some(joint) = joints.first() {
+                if moving.last() != Some(joint) {
+                    moving.push(*joint);
+                }
+            }
+        }
+        assert_eq!(moving, [0, 1, 2]);
+        assert_eq!(&table[table.len() - 1].position[..], &end[..]);
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+
+use crate::collision::{self, collide};
+use crate::dynamics::GRAVITY;
+use crate::geometry::{polygon_centroid, polygon_inertia, Vec2};
+#[cfg(feature = "rapier")]
+use crate::rapier::RapierPhysics;
+use crate::robot::{Part, Parts};
+
+// velocity passes over all contacts per step, more passes settle stacks better
+const ITERATIONS: usize = 30;
+// coulomb friction between objects and with the ground
+const FRICTION: f32 = 0.5;
+// passes that push overlapping objects apart after they moved
+const POSITION_ITERATIONS: usize = 4;
+// penetration that is tolerated to keep resting contacts alive, in units
+const SLOP: f32 = 0.0005;
+// fraction of the remaining penetration removed per position pass
+const CORRECTION: f32 = 0.8;
+// supported objects slower than this in units (degrees) per second are at rest
+const REST_SPEED: f32 = 0.01;
+const REST_ANGULAR_SPEED: f32 = 1.0;
+
+// motion state of a free object
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Body {
+    // units per second
+    pub velocity: Vec2,
+    // degrees per second counter-clockwise
+    pub angular_velocity: f32,
+    // supported by the ground or another object and not moving
+    pub resting: bool,
+}
+
+// object taking part in a step
+struct Item {
+    name: String,
+    vertices: Vec<Vec2>,
+    center: Vec2,
+    // zero for fixtures and objects moved by the arm, they push the others but are never pushed
+    inverse_mass: f32,
+    inverse_inertia: f32,
+    velocity: Vec2,
+    // radians per second
+    angular_velocity: f32,
+    supported: bool,
+    resting: bool,
+}
+
+impl Item {
+    fn translate(&mut self, offset: Vec2) {
+        self.vertices.iter_mut().for_each(|v| *v += offset);
+        self.center += offset;
+    }
+
+    // velocity of the point at `r` from the center
+    fn point_velocity(&self, r: Vec2) -> Vec2 {
+        self.velocity + r.perp() * self.angular_velocity
+    }
+
+    fn apply_impulse(&mut self, r: Vec2, impulse: Vec2) {
+        self.velocity += impulse * self.inverse_mass;
+        self.angular_velocity += r.cross(impulse) * self.inverse_inertia;
+    }
+}
+
+// contact point of a manifold
+struct ContactPoint {
+    // from the centers to the contact point
+    r: Vec2,
+    r_other: Vec2,
+    // impulses accumulated over the passes
+    impulse: f32,
+    friction: f32,
+}
+
+// contact where `item` is pushed along `normal` by `other`, or by the ground
+struct Contact {
+    item: usize,
+    other: Option<usize>,
+    normal: Vec2,
+    points: Vec<ContactPoint>,
+}
+
+// engine that moves the objects of the workcell
+pub trait PhysicsBackend {
+    fn body(&self, name: &str) -> Option<Body>;
+    // set the motion of an object, e.g. when it is released while the arm moves
+    fn set_velocity(&mut self, name: &str, velocity: Vec2, angular_velocity: f32);
+    // advance all objects by `dt` seconds, `held` objects are carried by the arm, `arm` are the
+    // parts of the arm at their current position and `fixtures` never move
+    fn step(
+        &mut self,
+        objects: &mut Parts,
+        arm: &[&dyn Part],
+        fixtures: &[&dyn Part],
+        ground: f32,
+        held: &[&str],
+        dt: f32,
+    );
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PhysicsMode {
+    // lightweight, objects fall, tip and stack but the arm passes through them
+    Simple,
+    // rigid body engine, the arm links push objects as well
+    #[cfg(feature = "rapier")]
+    Rapier,
+}
+
+impl Default for PhysicsMode {
+    fn default() -> Self {
+        #[cfg(feature = "rapier")]
+        return PhysicsMode::Rapier;
+        #[cfg(not(feature = "rapier"))]
+        return PhysicsMode::Simple;
+    }
+}
+
+impl PhysicsMode {
+    // cycle through the modes that are built in
+    pub fn next(self) -> Self {
+        match self {
+            #[cfg(feature = "rapier")]
+            PhysicsMode::Simple => PhysicsMode::Rapier,
+            #[cfg(feature = "rapier")]
+            PhysicsMode::Rapier => PhysicsMode::Simple,
+            #[cfg(not(feature = "rapier"))]
+            PhysicsMode::Simple => PhysicsMode::Simple,
+        }
+    }
+}
+
+pub fn create_backend(mode: PhysicsMode) -> Box<dyn PhysicsBackend> {
+    match mode {
+        PhysicsMode::Simple => Box::new(ObjectPhysics::default()),
+        #[cfg(feature = "rapier")]
+        PhysicsMode::Rapier => Box::new(RapierPhysics::default()),
+    }
+}
+
+// falling, tipping and stacking of the objects in the workcell, objects are rigid convex polygons
+#[derive(Clone, Debug, Default)]
+pub struct ObjectPhysics {
+    bodies: HashMap<String, Body>,
+}
+
+impl PhysicsBackend for ObjectPhysics {
+    fn body(&self, name: &str) -> Option<Body> {
+        self.bodies.get(name).copied()
+    }
+
+    fn set_velocity(&mut self, name: &str, velocity: Vec2, angular_velocity: f32) {
+        self.bodies.insert(
+            name.to_string(),
+            Body {
+                velocity,
+                angular_velocity,
+                resting: false,
+            },
+        );
+    }
+
+    fn step(
+        &mut self,
+        objects: &mut Parts,
+        _: &[&dyn Part],
+        fixtures: &[&dyn Part],
+        ground: f32,
+        held: &[&str],
+        dt: f32,
+    ) {
+        self.bodies.retain(|name, _| objects.contains_key(name));
+
+        let mut items: Vec<Item> = objects
+            .iter()
+            .map(|(name, object)| {
+                let body = self.bodies.get(name).copied().unwrap_or_default();
+                let vertices = object.get_vertices_ref().clone();
+                let center = polygon_centroid(&vertices);
+                let mut item = Item {
+                    name: name.clone(),
+                    vertices,
+                    center,
+                    inverse_mass: 0.0,
+                    inverse_inertia: 0.0,
+                    velocity: Vec2::ZERO,
+                    angular_velocity: 0.0,
+                    supported: false,
+                    resting: false,
+                };
+                if !held.contains(&name.as_str()) {
+                    let mass = object.get_mass().max(1e-6);
+                    item.inverse_mass = 1.0 / mass;
+                    item.inverse_inertia = 1.0 / polygon_inertia(&item.vertices, mass).max(1e-9);
+                    item.velocity = body.velocity + Vec2::new(0.0, -GRAVITY * dt);
+                    item.angular_velocity = body.angular_velocity.to_radians();
+                }
+                item
+            })
+            .collect();
+        // fixtures come after the objects and are left out when the objects are written back
+        items.extend(fixtures.iter().map(|fixture| {
+            let vertices = fixture.get_vertices_ref().clone();
+            Item {
+                name: String::new(),
+                center: polygon_centroid(&vertices),
+                vertices,
+                inverse_mass: 0.0,
+                inverse_inertia: 0.0,
+                velocity: Vec2::ZERO,
+                angular_velocity: 0.0,
+                supported: false,
+                resting: false,
+            }
+        }));
+
+        let mut contacts = find_contacts(&mut items, ground);
+        for _ in 0..ITERATIONS {
+            for contact in contacts.iter_mut() {
+                resolve(&mut items, contact);
+            }
+        }
+
+        for item in items.iter_mut() {
+            item.resting = item.supported
+                && item.velocity.length() < REST_SPEED
+                && item.angular_velocity.to_degrees().abs() < REST_ANGULAR_SPEED;
+            if item.resting {
+                // objects at rest stay put instead of creeping by the remaining contact jitter
+                item.velocity = Vec2::ZERO;
+                item.angular_velocity = 0.0;
+            }
+            let (offset, angle) = (
+                item.velocity * dt,
+                (item.angular_velocity * dt).to_degrees(),
+            );
+            let center = item.center;
+            item.vertices
+                .iter_mut()
+                .for_each(|v| *v = v.rotated_about(center, angle) + offset);
+            item.center += offset;
+        }
+        for _ in 0..POSITION_ITERATIONS {
+            separate(&mut items, ground);
+        }
+
+        items.truncate(objects.len());
+        for item in items {
+            if item.inverse_mass > 0.0 {
+                *objects.get_mut(&item.name).unwrap().get_vertices() = item.vertices;
+            }
+            self.bodies.insert(
+                item.name,
+                Body {
+                    velocity: item.velocity,
+                    angular_velocity: item.angular_velocity.to_degrees(),
+                    resting: item.resting,
+                },
+            );
+        }
+    }
+}
+
+// push the free objects out of the ground and out of each other
+fn separate(items: &mut [Item], ground: f32) {
+    for item in items.iter_mut().filter(|item| item.inverse_mass > 0.0) {
+        let lowest = item
+            .vertices
+            .iter()
+            .map(|v| v.y)
+            .fold(f32::INFINITY, f32::min);
+        if lowest < ground - SLOP {
+            item.translate(Vec2::new(0.0, (ground - SLOP - lowest) * CORRECTION));
+        }
+    }
+    for i in 0..items.len() {
+        let (before, after) = items.split_at_mut(i + 1);
+        let a = &mut before[i];
+        for b in after.iter_mut() {
+            let total = a.inverse_mass + b.inverse_mass;
+            if total <= 0.0 {
+                continue;
+            }
+            let Some(contact) = collide(&a.vertices, &b.vertices) else {
+                continue;
+            };
+            let push = contact.normal * ((contact.depth - SLOP).max(0.0) * CORRECTION / total);
+            a.translate(-push * a.inverse_mass);
+            b.translate(push * b.inverse_mass);
+        }
+    }
+}
+
+// contacts of the free objects with the ground and with each other
+fn find_contacts(items: &mut [Item], ground: f32) -> Vec<Contact> {
+    let point = |r, r_other| ContactPoint {
+        r,
+        r_other,
+        impulse: 0.0,
+        friction: 0.0,
+    };
+    let mut contacts = Vec::new();
+    for (i, item) in items.iter_mut().enumerate() {
+        if item.inverse_mass <= 0.0 {
+            continue;
+        }
+        let points: Vec<ContactPoint> = item
+            .vertices
+            .iter()
+            .filter(|v| v.y < ground)
+            .map(|v| point(*v - item.center, Vec2::ZERO))
+            .collect();
+        if !points.is_empty() {
+            item.supported = true;
+            contacts.push(Contact {
+                item: i,
+                other: None,
+                normal: Vec2::new(0.0, 1.0),
+                points,
+            });
+        }
+    }
+    for i in 0..items.len() {
+        for j in i + 1..items.len() {
+            let (a, b) = (&items[i], &items[j]);
+            if a.inverse_mass + b.inverse_mass <= 0.0 {
+                continue;
+            }
+            let Some(collision::Contact { normal, points, .. }) = collide(&a.vertices, &b.vertices)
+            else {
+                continue;
+            };
+            let points = points
+                .into_iter()
+                .map(|(p, _)| point(p - b.center, p - a.center))
+                .collect();
+            contacts.push(Contact {
+                item: j,
+                other: Some(i),
+                normal,
+                points,
+            });
+            // the object on top is supported by the one below
+            if normal.y > 0.5 {
+                items[j].supported = true;
+            } else if normal.y < -0.5 {
+                items[i].supported = true;
+            }
+        }
+    }
+    contacts
+}
+
+// inelastic impulses with coulomb friction at a contact, accumulated over the passes
+fn resolve(items: &mut [Item], contact: &mut Contact) {
+    let (normal, tangent) = (contact.normal, contact.normal.perp());
+    for point in contact.points.iter_mut() {
+        let relative = relative_velocity(items, contact.item, contact.other, point);
+        let resistance = resistance(items, contact.item, contact.other, point, point, tangent);
+        let friction = (point.friction - relative.dot(tangent) / resistance)
+            .clamp(-FRICTION * point.impulse, FRICTION * point.impulse);
+        apply(
+            items,
+            contact.item,
+            contact.other,
+            point,
+            tangent * (friction - point.friction),
+        );
+        point.friction = friction;
+    }
+
+    let (item, other) = (contact.item, contact.other);
+    match contact.points.as_mut_slice() {
+        // both points of a face contact at once, one after the other they would rock the object
+        [first, second] => {
+            let k11 = resistance(items, item, other, first, first, normal);
+            let k22 = resistance(items, item, other, second, second, normal);
+            let k12 = resistance(items, item, other, first, second, normal);
+            let old = (first.impulse, second.impulse);
+            let b1 = relative_velocity(items, item, other, first).dot(normal)
+                - k11 * old.0
+                - k12 * old.1;
+            let b2 = relative_velocity(items, item, other, second).dot(normal)
+                - k12 * old.0
+                - k22 * old.1;
+            let determinant = k11 * k22 - k12 * k12;
+            let both = (determinant.abs() > 1e-12).then(|| {
+                (
+                    (k12 * b2 - k22 * b1) / determinant,
+                    (k12 * b1 - k11 * b2) / determinant,
+                )
+            });
+            let (x1, x2) = match both {
+                Some((x1, x2)) if x1 >= 0.0 && x2 >= 0.0 => (x1, x2),
+                _ if -b1 / k11 >= 0.0 && k12 * -b1 / k11 + b2 >= 0.0 => (-b1 / k11, 0.0),
+                _ if -b2 / k22 >= 0.0 && k12 * -b2 / k22 + b1 >= 0.0 => (0.0, -b2 / k22),
+                _ => (0.0, 0.0),
+            };
+            apply(items, item, other, first, normal * (x1 - old.0));
+            apply(items, item, other, second, normal * (x2 - old.1));
+            first.impulse = x1;
+            second.impulse = x2;
+        }
+        points => {
+            for point in points.iter_mut() {
+                let approach = relative_velocity(items, item, other, point).dot(normal);
+                let resistance = resistance(items, item, other, point, point, normal);
+                let impulse = (point.impulse - approach / resistance).max(0.0);
+                apply(
+                    items,
+                    item,
+                    other,
+                    point,
+                    normal * (impulse - point.impulse),
+                );
+                point.impulse = impulse;
+            }
+        }
+    }
+}
+
+fn relative_velocity(
+    items: &[Item],
+    item: usize,
+    other: Option<usize>,
+    point: &ContactPoint,
+) -> Vec2 {
+    items[item].point_velocity(point.r)
+        - other.map_or(Vec2::ZERO, |other| {
+            items[other].point_velocity(point.r_other)
+        })
+}
+
+// velocity change at `first` along `direction` per unit impulse at `second`
+fn resistance(
+    items: &[Item],
+    item: usize,
+    other: Option<usize>,
+    first: &ContactPoint,
+    second: &ContactPoint,
+    direction: Vec2,
+) -> f32 {
+    let coupling = |item: &Item, r1: Vec2, r2: Vec2| {
+        item.inverse_mass + r1.cross(direction) * r2.cross(direction) * item.inverse_inertia
+    };
+    coupling(&items[item], first.r, second.r)
+        + other.map_or(0.0, |other| {
+            coupling(&items[other], first.r_other, second.r_other)
+        })
+}
+
+fn apply(
+    items: &mut [Item],
+    item: usize,
+    other: Option<usize>,
+    point: &ContactPoint,
+    impulse: Vec2,
+) {
+    items[item].apply_impulse(point.r, impulse);
+    if let Some(other) = other {
+        items[other].apply_impulse(point.r_other, -impulse);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::{generate_fixture, generate_object, CellDescription, GROUND};
+
+    const DT: f32 = 1.0 / 200.0;
+
+    fn square(x: f32, y: f32, size: f32, angle: f32) -> Box<dyn Part> {
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+        let center = Vec2::new(x + size / 2.0, y + size / 2.0);
+        let vertices = corners
+            .iter()
+            .map(|(dx, dy)| Vec2::new(x + dx, y + dy).rotated_about(center, angle))
+            .collect();
+        Box::new(generate_object(vertices, [0.0; 3], 0.5))
+    }
+
+    fn lowest(part: &dyn Part) -> f32 {
+        part.get_vertices_ref()
+            .iter()
+            .map(|v| v.y)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    fn simulate(objects: &mut Parts, fixtures: &[&dyn Part], seconds: f32) -> ObjectPhysics {
+        let mut physics = ObjectPhysics::default();
+        for _ in 0..(seconds / DT) as usize {
+            physics.step(objects, &[], fixtures, GROUND, &[], DT);
+        }
+        physics
+    }
+
+    #[test]
+    fn stacked_boxes_come_to_rest() {
+        let mut objects = Parts::new();
+        objects.insert("bottom".to_string(), square(0.0, GROUND + 0.05, 0.1, 0.0));
+        objects.insert("top".to_string(), square(0.02, GROUND + 0.2, 0.06, 0.0));
+        let physics = simulate(&mut objects, &[], 3.0);
+
+        for name in ["bottom", "top"] {
+            assert!(
+                physics.body(name).unwrap().resting,
+                "{} is not resting",
+                name
+            );
+        }
+        let (bottom, top) = (objects["bottom"].as_ref(), objects["top"].as_ref());
+        assert!((lowest(bottom) - GROUND).abs() <= 2.0 * SLOP);
+        assert!((lowest(top) - (GROUND + 0.1)).abs() <= 3.0 * SLOP);
+        let overlap = collide(bottom.get_vertices_ref(), top.get_vertices_ref())
+            .map_or(0.0, |contact| contact.depth);
+        assert!(overlap <= 2.0 * SLOP);
+    }
+
+    #[test]
+    fn tilted_box_settles_flat_on_the_table() {
+        let table = CellDescription::default()
+            .fixtures
+            .into_iter()
+            .find(|fixture| fixture.name == "table")
+            .unwrap();
+        let table = generate_fixture(table.vertices, table.color);
+        let mut objects = Parts::new();
+        objects.insert("box".to_string(), square(0.4, -0.17, 0.06, 25.0));
+        let physics = simulate(&mut objects, &[&table], 4.0);
+
+        assert!(physics.body("box").unwrap().resting);
+        let vertices = objects["box"].get_vertices_ref();
+        // on the table top with an edge flat on it
+        assert!((lowest(objects["box"].as_ref()) + 0.22).abs() <= 2.0 * SLOP);
+        let edge = vertices[1] - vertices[0];
+        let angle = edge.y.atan2(edge.x).to_degrees().rem_euclid(90.0);
+        assert!(angle.min(90.0 - angle) < 1.0, "tilted by {}", angle);
+    }
+}
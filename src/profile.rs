@@ -0,0 +1,342 @@
+// shape of the velocity over time of a single axis
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ProfileKind {
+    // constant acceleration, the velocity ramps up and down linearly
+    #[default]
+    Trapezoidal,
+    // limited jerk, the acceleration ramps up and down as well
+    SCurve,
+}
+
+// limits of a single axis, per second, squared and cubed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KinematicLimits {
+    pub velocity: f32,
+    pub acceleration: f32,
+    pub jerk: f32,
+}
+
+impl KinematicLimits {
+    pub const ZERO: KinematicLimits = KinematicLimits {
+        velocity: 0.0,
+        acceleration: 0.0,
+        jerk: 0.0,
+    };
+}
+
+// state of a single axis
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct ProfilePoint {
+    pub position: f32,
+    pub velocity: f32,
+    pub acceleration: f32,
+}
+
+impl ProfilePoint {
+    pub fn at_rest(position: f32) -> ProfilePoint {
+        ProfilePoint {
+            position,
+            ..Default::default()
+        }
+    }
+
+    // state after `t` seconds with constant `jerk`
+    fn advance(&self, jerk: f32, t: f32) -> ProfilePoint {
+        ProfilePoint {
+            position: self.position
+                + self.velocity * t
+                + self.acceleration * t * t / 2.0
+                + jerk * t * t * t / 6.0,
+            velocity: self.velocity + self.acceleration * t + jerk * t * t / 2.0,
+            acceleration: self.acceleration + jerk * t,
+        }
+    }
+}
+
+// part of a profile with constant jerk
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Segment {
+    duration: f32,
+    start: ProfilePoint,
+    jerk: f32,
+}
+
+// rest to rest motion of a single axis from 0 to `distance`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    segments: Vec<Segment>,
+    distance: f32,
+}
+
+impl Profile {
+    pub fn new(kind: ProfileKind, distance: f32, limits: KinematicLimits) -> Profile {
+        let KinematicLimits {
+            velocity,
+            acceleration,
+            jerk,
+        } = limits;
+        if distance <= 0.0 {
+            return Profile {
+                segments: Vec::new(),
+                distance: 0.0,
+            };
+        }
+
+        // duration, acceleration at the start and jerk of each phase
+        let phases = match kind {
+            ProfileKind::Trapezoidal => {
+                // short moves never reach the velocity limit
+                let v = velocity.min((distance * acceleration).sqrt());
+                let ramp = v / acceleration;
+                let cruise = (distance - v * ramp) / v;
+                vec![
+                    (ramp, acceleration, 0.0),
+                    (cruise, 0.0, 0.0),
+                    (ramp, -acceleration, 0.0),
+                ]
+            }
+            ProfileKind::SCurve => {
+                let v = velocity.min(s_curve_velocity(distance, acceleration, jerk));
+                // short ramps never reach the acceleration limit
+                let a = acceleration.min((v * jerk).sqrt());
+                let jerk_time = a / jerk;
+                let ramp = v / a + jerk_time;
+                let cruise = (distance - v * ramp) / v;
+                let constant = ramp - 2.0 * jerk_time;
+                vec![
+                    (jerk_time, 0.0, jerk),
+                    (constant, a, 0.0),
+                    (jerk_time, a, -jerk),
+                    (cruise, 0.0, 0.0),
+                    (jerk_time, 0.0, -jerk),
+                    (constant, -a, 0.0),
+                    (jerk_time, -a, jerk),
+                ]
+            }
+        };
+
+        let mut point = ProfilePoint::default();
+        let mut segments = Vec::with_capacity(phases.len());
+        for (duration, acceleration, jerk) in phases {
+            let duration = duration.max(0.0);
+            let start = ProfilePoint {
+                acceleration,
+                ..point
+            };
+            segments.push(Segment {
+                duration,
+                start,
+                jerk,
+            });
+            point = start.advance(jerk, duration);
+        }
+        Profile { segments, distance }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.segments.iter().map(|segment| segment.duration).sum()
+    }
+
+    // state at `t` seconds, clamped to the duration
+    pub fn sample(&self, mut t: f32) -> ProfilePoint {
+        // the summed segments may fall short of the distance by rounding
+        if t >= self.duration() {
+            return ProfilePoint::at_rest(self.distance);
+        }
+        for segment in self.segments.iter() {
+            if t < segment.duration {
+                return segment.start.advance(segment.jerk, t.max(0.0));
+            }
+            t -= segment.duration;
+        }
+        ProfilePoint::at_rest(self.distance)
+    }
+}
+
+// peak velocity of an S-curve covering `distance` without cruising
+fn s_curve_velocity(distance: f32, acceleration: f32, jerk: f32) -> f32 {
+    let v = acceleration
+        * (-acceleration / jerk
+            + (acceleration * acceleration / (jerk * jerk) + 4.0 * distance / acceleration).sqrt())
+        / 2.0;
+    if v * jerk >= acceleration * acceleration {
+        v
+    } else {
+        // the acceleration limit is not reached either
+        (distance * jerk.sqrt() / 2.0).powf(2.0 / 3.0)
+    }
+}
+
+// jogged axis, each jog moves rest to rest and a further jog in the same direction extends the
+// running profile as long as it has not started braking yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct JogAxis {
+    origin: f32,
+    target: f32,
+    profile: Profile,
+    elapsed: f32,
+    // target of a jog that could not extend the running profile
+    pending: Option<f32>,
+}
+
+impl JogAxis {
+    pub fn new(position: f32) -> JogAxis {
+        JogAxis {
+            origin: position,
+            target: position,
+            profile: Profile::new(ProfileKind::default(), 0.0, KinematicLimits::ZERO),
+            elapsed: 0.0,
+            pending: None,
+        }
+    }
+
+    // latest requested target
+    pub fn target(&self) -> f32 {
+        self.pending.unwrap_or(self.target)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_none() && self.elapsed >= self.profile.duration()
+    }
+
+    pub fn position(&self) -> f32 {
+        let direction = (self.target - self.origin).signum();
+        self.origin + direction * self.profile.sample(self.elapsed).position
+    }
+
+    pub fn set_target(&mut self, target: f32, limits: KinematicLimits, kind: ProfileKind) {
+        if self.is_done() {
+            self.start(target, limits, kind);
+            return;
+        }
+        let extends = (target - self.origin) * (self.target - self.origin) > 0.0
+            && (target - self.origin).abs() > (self.target - self.origin).abs();
+        if extends && self.pending.is_none() {
+            let profile = Profile::new(kind, (target - self.origin).abs(), limits);
+            let (old, new) = (
+                self.profile.sample(self.elapsed),
+                profile.sample(self.elapsed),
+            );
+            if (old.position - new.position).abs() < 1e-4
+                && (old.velocity - new.velocity).abs() < 1e-3
+            {
+                self.target = target;
+                self.profile = profile;
+                return;
+            }
+        }
+        self.pending = Some(target);
+    }
+
+    // advance by `dt` seconds and return the new position
+    pub fn advance(&mut self, dt: f32, limits: KinematicLimits, kind: ProfileKind) -> f32 {
+        self.elapsed += dt;
+        if self.elapsed >= self.profile.duration() {
+            if let Some(target) = self.pending.take() {
+                self.start(target, limits, kind);
+            }
+        }
+        self.position()
+    }
+
+    fn start(&mut self, target: f32, limits: KinematicLimits, kind: ProfileKind) {
+        self.origin = self.position();
+        self.target = target;
+        self.profile = Profile::new(kind, (target - self.origin).abs(), limits);
+        self.elapsed = 0.0;
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: KinematicLimits = KinematicLimits {
+        velocity: 60.0,
+        acceleration: 240.0,
+        jerk: 1800.0,
+    };
+    const DT: f32 = 0.001;
+
+    // short moves reach neither the velocity nor the acceleration limit, long ones cruise
+    const DISTANCES: [f32; 5] = [0.1, 2.0, 10.0, 30.0, 200.0];
+
+    fn samples(profile: &Profile) -> Vec<ProfilePoint> {
+        let steps = (profile.duration() / DT).ceil() as usize;
+        (0..=steps).map(|i| profile.sample(i as f32 * DT)).collect()
+    }
+
+    #[test]
+    fn ends_at_the_distance() {
+        for kind in [ProfileKind::Trapezoidal, ProfileKind::SCurve] {
+            for distance in DISTANCES {
+                let profile = Profile::new(kind, distance, LIMITS);
+                assert_eq!(
+                    profile.sample(profile.duration()),
+                    ProfilePoint::at_rest(distance)
+                );
+                // the last segment brakes onto the distance
+                let end = profile.sample(profile.duration() - 1e-4);
+                assert!((end.position - distance).abs() < 1e-3 * distance.max(1.0));
+                assert!(end.velocity.abs() < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn stays_within_the_limits() {
+        for kind in [ProfileKind::Trapezoidal, ProfileKind::SCurve] {
+            for distance in DISTANCES {
+                let profile = Profile::new(kind, distance, LIMITS);
+                let samples = samples(&profile);
+                for point in samples.iter() {
+                    assert!(point.velocity >= -1e-3);
+                    assert!(point.velocity <= LIMITS.velocity * 1.001);
+                    assert!(point.acceleration.abs() <= LIMITS.acceleration * 1.001);
+                    assert!(point.position <= distance * 1.001);
+                }
+                if kind == ProfileKind::SCurve {
+                    for pair in samples.windows(2) {
+                        let jerk = (pair[1].acceleration - pair[0].acceleration) / DT;
+                        assert!(jerk.abs() <= LIMITS.jerk * 1.01);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_jogs_extend_the_target() {
+        // simulation steps
+        let dt = 0.005;
+        for kind in [ProfileKind::Trapezoidal, ProfileKind::SCurve] {
+            let mut axis = JogAxis::new(-10.0);
+            axis.set_target(-7.0, LIMITS, kind);
+            let mut positions = vec![-10.0];
+            // the key is pressed again while the joint is still speeding up
+            for press in 0..3 {
+                for _ in 0..10 {
+                    positions.push(axis.advance(dt, LIMITS, kind));
+                }
+                axis.set_target(axis.target() + 3.0, LIMITS, kind);
+                assert_eq!(axis.target(), -4.0 + 3.0 * press as f32);
+            }
+            while !axis.is_done() {
+                positions.push(axis.advance(dt, LIMITS, kind));
+            }
+            assert_eq!(*positions.last().unwrap(), 2.0);
+
+            let velocities: Vec<f32> = positions.windows(2).map(|p| (p[1] - p[0]) / dt).collect();
+            for pair in velocities.windows(2) {
+                assert!((pair[1] - pair[0]).abs() <= LIMITS.acceleration * dt * 1.01);
+            }
+            // one move, the joint does not stop in between
+            let stops = velocities[1..velocities.len() - 1]
+                .iter()
+                .filter(|v| v.abs() < 1e-3)
+                .count();
+            assert_eq!(stops, 0);
+        }
+    }
+}
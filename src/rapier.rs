@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use rapier2d::prelude::{
+    ColliderBuilder, ColliderHandle, Group, InteractionGroups, InteractionTestMode, PhysicsWorld,
+    Pose, RigidBodyBuilder, RigidBodyHandle, RigidBodyType, Vector,
+};
+
+use crate::collision::convex_pieces;
+use crate::dynamics::GRAVITY;
+use crate::geometry::{polygon_centroid, Vec2};
+use crate::physics::{Body, PhysicsBackend};
+use crate::robot::{Part, Parts};
+
+// coulomb friction of all colliders
+const FRICTION: f32 = 0.5;
+// half height of the ground slab below the ground level
+const GROUND_DEPTH: f32 = 1.0;
+// moving objects slower than this in units (degrees) per second are at rest
+const REST_SPEED: f32 = 0.01;
+const REST_ANGULAR_SPEED: f32 = 1.0;
+// objects further than this from their body (in units or radians) were moved from outside
+const MOVED: f32 = 1.0e-4;
+// solver group of the arm parts, objects that pass through the arm leave it out of their filter
+const ARM: Group = Group::GROUP_2;
+
+// part that has a body in the engine, the shape is stored around its centroid
+struct Tracked {
+    body: RigidBodyHandle,
+    collider: ColliderHandle,
+    local: Vec<Vec2>,
+}
+
+// rigid body engine backend, objects are dynamic bodies and the arm parts are kinematic bodies
+// that follow the joints
+#[derive(Default)]
+pub struct RapierPhysics {
+    world: PhysicsWorld,
+    objects: HashMap<String, Tracked>,
+    arm: Vec<Tracked>,
+    ground: Option<(RigidBodyHandle, f32)>,
+    // fixed bodies of the fixtures and the shapes they were made of
+    fixtures: Vec<(RigidBodyHandle, Vec<Vec2>)>,
+    // released objects that still overlap the claws, they fall out of them instead of being
+    // pushed away
+    passing: HashSet<String>,
+}
+
+impl PhysicsBackend for RapierPhysics {
+    fn body(&self, name: &str) -> Option<Body> {
+        let tracked = self.objects.get(name)?;
+        let body = &self.world.bodies[tracked.body];
+        let (velocity, angular_velocity) = (body.linvel(), body.angvel().to_degrees());
+        let velocity = Vec2::new(velocity.x, velocity.y);
+        let touching = self
+            .world
+            .contact_pairs_with(tracked.collider)
+            .any(|pair| pair.has_any_active_contact());
+        Some(Body {
+            velocity,
+            angular_velocity,
+            resting: body.is_sleeping()
+                || touching
+                    && velocity.length() < REST_SPEED
+                    && angular_velocity.abs() < REST_ANGULAR_SPEED,
+        })
+    }
+
+    fn set_velocity(&mut self, name: &str, velocity: Vec2, angular_velocity: f32) {
+        if let Some(tracked) = self.objects.get(name) {
+            pass_arm(&mut self.world, tracked, true);
+            self.passing.insert(name.to_string());
+            let body = &mut self.world.bodies[tracked.body];
+            body.set_body_type(RigidBodyType::Dynamic, true);
+            body.set_linvel(Vector::new(velocity.x, velocity.y), true);
+            body.set_angvel(angular_velocity.to_radians(), true);
+        }
+    }
+
+    fn step(
+        &mut self,
+        objects: &mut Parts,
+        arm: &[&dyn Part],
+        fixtures: &[&dyn Part],
+        ground: f32,
+        held: &[&str],
+        dt: f32,
+    ) {
+        self.set_ground(ground);
+        self.set_fixtures(fixtures);
+
+        // objects that were removed, added or changed their shape
+        let world = &mut self.world;
+        self.objects.retain(|name, tracked| {
+            let keep = objects
+                .get(name)
+                .is_some_and(|object| object.get_vertices_ref().len() == tracked.local.len());
+            if !keep {
+                world.remove_body(tracked.body);
+            }
+            keep
+        });
+        for (name, object) in objects.iter() {
+            if !self.objects.contains_key(name) {
+                let tracked = track(world, object.get_vertices_ref(), Some(object.get_mass()));
+                self.objects.insert(name.clone(), tracked);
+            }
+        }
+        if self.arm.len() != arm.len() {
+            for tracked in self.arm.drain(..) {
+                world.remove_body(tracked.body);
+            }
+            self.arm = arm
+                .iter()
+                .map(|part| track(world, part.get_vertices_ref(), None))
+                .collect();
+        }
+
+        // parts moved by the arm follow it, everything else is simulated
+        for (tracked, part) in self.arm.iter().zip(arm.iter()) {
+            world.bodies[tracked.body]
+                .set_next_kinematic_position(pose(tracked, part.get_vertices_ref()));
+        }
+        for (name, tracked) in self.objects.iter() {
+            let pose = pose(tracked, objects[name].get_vertices_ref());
+            if !held.contains(&name.as_str()) && !world.bodies[tracked.body].is_dynamic() {
+                pass_arm(world, tracked, true);
+                self.passing.insert(name.clone());
+            }
+            let body = &mut world.bodies[tracked.body];
+            if held.contains(&name.as_str()) {
+                body.set_body_type(RigidBodyType::KinematicPositionBased, true);
+                body.set_next_kinematic_position(pose);
+            } else {
+                body.set_body_type(RigidBodyType::Dynamic, true);
+                // objects that were moved from outside, e.g. by the claws just before they let go,
+                // are placed there
+                let position = body.position();
+                let turn = (position.rotation.angle() - pose.rotation.angle()).rem_euclid(TAU);
+                if (position.translation - pose.translation).length() > MOVED
+                    || turn.min(TAU - turn) > MOVED
+                {
+                    body.set_position(pose, true);
+                }
+            }
+        }
+
+        world.integration_parameters.dt = dt;
+        world.step();
+
+        let arm = &self.arm;
+        self.passing.retain(|name| {
+            let Some(tracked) = self.objects.get(name) else {
+                return false;
+            };
+            let overlapping = world.contact_pairs_with(tracked.collider).any(|pair| {
+                arm.iter()
+                    .any(|part| part.collider == pair.collider1 || part.collider == pair.collider2)
+                    && pair
+                        .manifolds()
+                        .iter()
+                        .any(|manifold| manifold.points.iter().any(|point| point.dist < 0.0))
+            });
+            if !overlapping {
+                pass_arm(world, tracked, false);
+            }
+            overlapping
+        });
+
+        for (name, tracked) in self.objects.iter() {
+            if held.contains(&name.as_str()) {
+                continue;
+            }
+            let pose = world.bodies[tracked.body].position();
+            *objects.get_mut(name).unwrap().get_vertices() = tracked
+                .local
+                .iter()
+                .map(|v| {
+                    let v = pose.transform_point(Vector::new(v.x, v.y));
+                    Vec2::new(v.x, v.y)
+                })
+                .collect();
+        }
+    }
+}
+
+impl RapierPhysics {
+    fn set_ground(&mut self, ground: f32) {
+        if self.ground.is_some_and(|(_, level)| level == ground) {
+            return;
+        }
+        if let Some((body, _)) = self.ground.take() {
+            self.world.remove_body(body);
+        }
+        self.world.gravity = Vector::new(0.0, -GRAVITY);
+        let (body, _) = self.world.insert(
+            RigidBodyBuilder::fixed().translation(Vector::new(0.0, ground - GROUND_DEPTH)),
+            ColliderBuilder::cuboid(1.0e3, GROUND_DEPTH).friction(FRICTION),
+        );
+        self.ground = Some((body, ground));
+    }
+
+    // fixtures get a fixed body with a collider for each convex piece of their shape
+    fn set_fixtures(&mut self, fixtures: &[&dyn Part]) {
+        if self.fixtures.len() == fixtures.len()
+            && self
+                .fixtures
+                .iter()
+                .zip(fixtures.iter())
+                .all(|((_, vertices), fixture)| vertices == fixture.get_vertices_ref())
+        {
+            return;
+        }
+        for (body, _) in self.fixtures.drain(..) {
+            self.world.remove_body(body);
+        }
+        for fixture in fixtures {
+            let vertices = fixture.get_vertices_ref();
+            let body = self.world.bodies.insert(RigidBodyBuilder::fixed());
+            for piece in convex_pieces(vertices) {
+                let points: Vec<Vector> = piece.iter().map(|v| Vector::new(v.x, v.y)).collect();
+                if let Some(collider) = ColliderBuilder::convex_hull(&points) {
+                    self.world.colliders.insert_with_parent(
+                        collider.friction(FRICTION),
+                        body,
+                        &mut self.world.bodies,
+                    );
+                }
+            }
+            self.fixtures.push((body, vertices.clone()));
+        }
+    }
+}
+
+// add a body for the polygon `vertices`, dynamic with `mass` or kinematic without
+fn track(world: &mut PhysicsWorld, vertices: &[Vec2], mass: Option<f32>) -> Tracked {
+    let center = polygon_centroid(vertices);
+    let local: Vec<Vec2> = vertices.iter().map(|v| *v - center).collect();
+    let points: Vec<Vector> = local.iter().map(|v| Vector::new(v.x, v.y)).collect();
+    // degenerate polygons get a tiny box so that they still have a body
+    let collider = ColliderBuilder::convex_hull(&points)
+        .unwrap_or_else(|| ColliderBuilder::cuboid(1.0e-3, 1.0e-3))
+        .friction(FRICTION);
+    let (body, collider) = match mass {
+        Some(mass) => world.insert(
+            RigidBodyBuilder::dynamic().translation(Vector::new(center.x, center.y)),
+            collider.mass(mass.max(1.0e-6)),
+        ),
+        None => world.insert(
+            RigidBodyBuilder::kinematic_position_based()
+                .translation(Vector::new(center.x, center.y)),
+            collider.solver_groups(InteractionGroups::new(
+                ARM,
+                Group::ALL,
+                InteractionTestMode::And,
+            )),
+        ),
+    };
+    Tracked {
+        body,
+        collider,
+        local,
+    }
+}
+
+// let the object of `tracked` pass through the arm parts or collide with them again
+fn pass_arm(world: &mut PhysicsWorld, tracked: &Tracked, passing: bool) {
+    let filter = if passing { !ARM } else { Group::ALL };
+    world.colliders[tracked.collider].set_solver_groups(InteractionGroups::new(
+        Group::ALL,
+        filter,
+        InteractionTestMode::And,
+    ));
+}
+
+// pose of the body of `tracked` when its polygon is at `vertices`
+fn pose(tracked: &Tracked, vertices: &[Vec2]) -> Pose {
+    let center = polygon_centroid(vertices);
+    // the vertex farthest from the center gives the best angle
+    let (local, vertex) = tracked
+        .local
+        .iter()
+        .zip(vertices.iter())
+        .max_by(|(a, _), (b, _)| a.length().total_cmp(&b.length()))
+        .map(|(local, vertex)| (*local, *vertex - center))
+        .unwrap_or_default();
+    let angle = local.cross(vertex).atan2(local.dot(vertex));
+    Pose::new(Vector::new(center.x, center.y), angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::{generate_random_object, GROUND};
+
+    #[test]
+    fn released_object_keeps_its_velocity() {
+        let dt = 1.0 / 200.0;
+        let mut physics = RapierPhysics::default();
+        let mut objects = Parts::new();
+        objects.insert(
+            "box".to_string(),
+            Box::new(generate_random_object((0.0, 0.0), [0.0; 3])),
+        );
+        // carried by the arm, then let go while moving to the right and turning
+        physics.step(&mut objects, &[], &[], GROUND, &["box"], dt);
+        let before = polygon_centroid(objects["box"].get_vertices_ref());
+        physics.set_velocity("box", Vec2::new(1.0, 0.5), 90.0);
+        physics.step(&mut objects, &[], &[], GROUND, &[], dt);
+
+        let body = physics.body("box").unwrap();
+        assert!((body.velocity.x - 1.0).abs() < 1e-3);
+        assert!((body.velocity.y - (0.5 - GRAVITY * dt)).abs() < 1e-3);
+        assert!((body.angular_velocity - 90.0).abs() < 1e-2);
+        assert!(!body.resting);
+        let moved = polygon_centroid(objects["box"].get_vertices_ref()) - before;
+        assert!((moved.x - dt).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,129 @@
+use glium::index::PrimitiveType;
+use glium::{glutin::surface::WindowSurface, Surface};
+use robot_simulation::geometry::Vec2;
+use robot_simulation::robot::{Color, Fixture, Part};
+use robot_simulation::world::World;
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 2],
+}
+implement_vertex!(Vertex, position);
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 330 core
+
+    in vec2 position;
+
+    void main() {
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const FRAGMENT_SHADER_SRC: &str = r#"
+    #version 330 core
+
+    uniform vec3 part_color;
+    out vec4 color;
+
+    void main() {
+        color = vec4(part_color, 1.0);
+    }
+"#;
+
+// draws the world model, holds no simulation state
+pub struct Renderer {
+    program: glium::Program,
+}
+
+impl Renderer {
+    pub fn new(disp: &glium::Display<WindowSurface>) -> Renderer {
+        let program =
+            glium::Program::from_source(disp, VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC, None)
+                .unwrap();
+        Renderer { program }
+    }
+
+    pub fn draw(
+        &self,
+        frame: &mut glium::Frame,
+        disp: &glium::Display<WindowSurface>,
+        world: &World,
+    ) {
+        // draw fixtures, objects, then chains
+        for fixture in world.fixtures.values() {
+            self.draw_fixture(frame, disp, fixture);
+        }
+        for object in world.objects.values() {
+            self.draw_part(frame, disp, object.as_ref());
+        }
+        for part in world.arm_parts() {
+            self.draw_part(frame, disp, part);
+        }
+    }
+
+    fn draw_part(
+        &self,
+        frame: &mut glium::Frame,
+        disp: &glium::Display<WindowSurface>,
+        part: &dyn Part,
+    ) {
+        self.draw_polygon(
+            frame,
+            disp,
+            part.get_vertices_ref(),
+            part.get_color(),
+            PrimitiveType::TriangleFan,
+        );
+    }
+
+    // fixtures may be concave, they are filled piece by piece and outlined in a darker shade
+    fn draw_fixture(
+        &self,
+        frame: &mut glium::Frame,
+        disp: &glium::Display<WindowSurface>,
+        fixture: &Fixture,
+    ) {
+        let color = fixture.get_color();
+        for piece in fixture.pieces() {
+            self.draw_polygon(frame, disp, piece, color, PrimitiveType::TriangleFan);
+        }
+        self.draw_polygon(
+            frame,
+            disp,
+            fixture.get_vertices_ref(),
+            color.map(|c| c * 0.4),
+            PrimitiveType::LineLoop,
+        );
+    }
+
+    fn draw_polygon(
+        &self,
+        frame: &mut glium::Frame,
+        disp: &glium::Display<WindowSurface>,
+        vertices: &[Vec2],
+        color: Color,
+        primitive: PrimitiveType,
+    ) {
+        let vertices: Vec<Vertex> = vertices
+            .iter()
+            .map(|v| Vertex {
+                position: [v.x, v.y],
+            })
+            .collect();
+        if vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = glium::VertexBuffer::new(disp, &vertices).unwrap();
+        frame
+            .draw(
+                &vertex_buffer,
+                glium::index::NoIndices(primitive),
+                &self.program,
+                &uniform! { part_color: color },
+                &Default::default(),
+            )
+            .unwrap();
+    }
+}
@@ -1,748 +1,604 @@
-pub mod robot {
-    use glium::{glutin::surface::WindowSurface, VertexBuffer};
-    use std::collections::HashMap;
-    use std::f32::consts::PI;
-    use std::thread;
-    use std::time::Duration;
-
-    #[derive(Copy, Clone, Debug)]
-    pub struct Vertex {
-        pub position: [f32; 2],
-    }
-    implement_vertex!(Vertex, position);
-
-    const DEF_RADIUS: f32 = 0.05;
-    const SLEEP_DURATION: Duration = Duration::from_millis(50);
-    pub const DEF_THINNING: f32 = 0.02;
-    pub const DEF_HEIGHT: f32 = 0.4;
-    pub const GROUND: f32 = -0.43;
-
-    pub struct State {
-        pub l1: i32,
-        pub l2: i32,
-        pub l3: i32,
-        pub l4: i32,
-        pub moved_object: String,
-    }
-
-    pub trait Part {
-        fn get_vertex_buf(&self) -> &glium::VertexBuffer<Vertex>;
-        fn set_vertex_buf(&mut self, value: VertexBuffer<Vertex>);
-        fn get_index_buf(&self) -> &glium::IndexBuffer<u32>;
-        fn get_program(&self) -> &glium::program::Program;
-        fn get_vertices(&mut self) -> &mut Vec<Vertex>;
-        fn get_vertices_ref(&self) -> &Vec<Vertex>;
-        fn get_tip(&mut self) -> Option<&mut Vertex>;
-    }
-
-    pub struct Chain {
-        // link and joint
-        pub vertices: Vec<Vertex>,
-        pub indices: Vec<u32>,
-        pub tip: Vertex,
-        pub vertex_buffer: glium::VertexBuffer<Vertex>,
-        pub index_buffer: glium::IndexBuffer<u32>,
-        pub program: glium::program::Program,
-    }
-
-    impl Part for Chain {
-        fn get_vertex_buf(&self) -> &glium::VertexBuffer<Vertex> {
-            &self.vertex_buffer
-        }
-        fn set_vertex_buf(&mut self, value: VertexBuffer<Vertex>) {
-            self.vertex_buffer = value;
-        }
-        fn get_index_buf(&self) -> &glium::IndexBuffer<u32> {
-            &self.index_buffer
-        }
-        fn get_program(&self) -> &glium::program::Program {
-            &self.program
-        }
-        fn get_vertices(&mut self) -> &mut Vec<Vertex> {
-            &mut self.vertices
-        }
-        fn get_vertices_ref(&self) -> &Vec<Vertex> {
-            &self.vertices
-        }
-        fn get_tip(&mut self) -> Option<&mut Vertex> {
-            Some(&mut self.tip)
-        }
+use crate::collision::{convex_contact, convex_pieces};
+use crate::geometry::Vec2;
+use crate::kinematics::{forward_kinematics, tcp_pose, wrist, Frame, JointState, BASE};
+use crate::profile::KinematicLimits;
+use crate::servo::ServoDescription;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::fmt;
+
+pub const DEF_RADIUS: f32 = 0.05;
+pub const DEF_THINNING: f32 = 0.02;
+pub const DEF_HEIGHT: f32 = 0.4;
+pub const DEF_CLAW_LENGTH: f32 = 0.06;
+pub const DEF_CLAW_DEPTH: f32 = 0.03;
+// fully open claws, the claw geometry is modelled in this configuration
+pub const DEF_CLAW_OPENING: f32 = 45.0;
+// degrees per second and degrees per key press
+pub const DEF_JOINT_VELOCITY: f32 = 60.0;
+pub const DEF_CLAW_VELOCITY: f32 = 100.0;
+pub const DEF_JOG_INCREMENT: f32 = 3.0;
+pub const DEF_CLAW_JOG_INCREMENT: f32 = 5.0;
+// degrees per second squared and cubed
+pub const DEF_JOINT_ACCELERATION: f32 = 240.0;
+pub const DEF_JOINT_JERK: f32 = 1800.0;
+// kilograms and newton meters, lengths count as meters
+pub const DEF_LINK_MASS: f32 = 1.0;
+pub const DEF_JOINT_TORQUE: f32 = 40.0;
+pub const DEF_OBJECT_MASS: f32 = 0.5;
+// newtons each claw presses on a grasped object and friction coefficient of the claws
+pub const DEF_GRIP_FORCE: f32 = 10.0;
+pub const DEF_GRIP_FRICTION: f32 = 0.5;
+pub const GROUND: f32 = -0.43;
+pub const DEF_FIXTURE_COLOR: Color = [0.5, 0.5, 0.5];
+
+pub type Color = [f32; 3];
+
+pub trait Part {
+    fn get_vertices(&mut self) -> &mut Vec<Vec2>;
+    fn get_vertices_ref(&self) -> &Vec<Vec2>;
+    fn get_tip(&self) -> Option<Vec2>;
+    fn get_color(&self) -> Color;
+    fn get_mass(&self) -> f32 {
+        0.0
     }
+}
 
-    pub struct Claw {
-        pub vertices: Vec<Vertex>,
-        pub tip: Vertex,
-        pub vertex_buffer: glium::VertexBuffer<Vertex>,
-        pub index_buffer: glium::IndexBuffer<u32>,
-        pub program: glium::program::Program,
-    }
+pub struct Chain {
+    // link and joint
+    pub vertices: Vec<Vec2>,
+    pub tip: Vec2,
+    pub color: Color,
+}
 
-    impl Part for Claw {
-        fn get_vertex_buf(&self) -> &glium::VertexBuffer<Vertex> {
-            &self.vertex_buffer
-        }
-        fn set_vertex_buf(&mut self, value: VertexBuffer<Vertex>) {
-            self.vertex_buffer = value;
-        }
-        fn get_index_buf(&self) -> &glium::IndexBuffer<u32> {
-            &self.index_buffer
-        }
-        fn get_program(&self) -> &glium::program::Program {
-            &self.program
-        }
-        fn get_vertices(&mut self) -> &mut Vec<Vertex> {
-            &mut self.vertices
-        }
-        fn get_vertices_ref(&self) -> &Vec<Vertex> {
-            &self.vertices
-        }
-        fn get_tip(&mut self) -> Option<&mut Vertex> {
-            Some(&mut self.tip)
-        }
+impl Part for Chain {
+    fn get_vertices(&mut self) -> &mut Vec<Vec2> {
+        &mut self.vertices
     }
-
-    pub struct Object {
-        pub vertices: Vec<Vertex>,
-        pub vertex_buffer: glium::VertexBuffer<Vertex>,
-        pub index_buffer: glium::IndexBuffer<u32>,
-        pub program: glium::program::Program,
+    fn get_vertices_ref(&self) -> &Vec<Vec2> {
+        &self.vertices
     }
-
-    impl Part for Object {
-        fn get_vertex_buf(&self) -> &glium::VertexBuffer<Vertex> {
-            &self.vertex_buffer
-        }
-        fn set_vertex_buf(&mut self, value: VertexBuffer<Vertex>) {
-            self.vertex_buffer = value;
-        }
-        fn get_index_buf(&self) -> &glium::IndexBuffer<u32> {
-            &self.index_buffer
-        }
-        fn get_program(&self) -> &glium::program::Program {
-            &self.program
-        }
-        fn get_vertices(&mut self) -> &mut Vec<Vertex> {
-            &mut self.vertices
-        }
-        fn get_vertices_ref(&self) -> &Vec<Vertex> {
-            &self.vertices
-        }
-        fn get_tip(&mut self) -> Option<&mut Vertex> {
-            None
-        }
+    fn get_tip(&self) -> Option<Vec2> {
+        Some(self.tip)
     }
-
-    pub fn generate_object(
-        vertices: Vec<Vertex>,
-        r: &str,
-        g: &str,
-        b: &str,
-        disp: &glium::Display<WindowSurface>,
-    ) -> Object {
-        let indices = (0..=3).collect();
-        let (vertex_buffer, index_buffer) = generate_vertex_index_buffer(disp, &vertices, &indices);
-        let program = generate_program(r, g, b, disp);
-        Object {
-            vertices,
-            vertex_buffer,
-            index_buffer,
-            program,
-        }
+    fn get_color(&self) -> Color {
+        self.color
     }
+}
 
-    pub fn generate_random_object(
-        bl: (f32, f32),
-        r: &str,
-        g: &str,
-        b: &str,
-        disp: &glium::Display<WindowSurface>,
-    ) -> Object {
-        let vertices = vec![
-            Vertex {
-                position: [bl.0, bl.1], //bl
-            },
-            Vertex {
-                position: [bl.0 + 0.05, bl.1], //br
-            },
-            Vertex {
-                position: [bl.0 + 0.05, bl.1 + 0.1], //tr
-            },
-            Vertex {
-                position: [bl.0, bl.1 + 0.1], //tl
-            },
-        ];
-        let indices = (0..=3).collect();
-        let (vertex_buffer, index_buffer) = generate_vertex_index_buffer(disp, &vertices, &indices);
-        let program = generate_program(r, g, b, disp);
-        Object {
-            vertices,
-            vertex_buffer,
-            index_buffer,
-            program,
-        }
+pub struct Claw {
+    pub vertices: Vec<Vec2>,
+    pub tip: Vec2,
+    pub color: Color,
+}
+
+impl Part for Claw {
+    fn get_vertices(&mut self) -> &mut Vec<Vec2> {
+        &mut self.vertices
+    }
+    fn get_vertices_ref(&self) -> &Vec<Vec2> {
+        &self.vertices
     }
+    fn get_tip(&self) -> Option<Vec2> {
+        Some(self.tip)
+    }
+    fn get_color(&self) -> Color {
+        self.color
+    }
+}
 
-    pub fn generate_claws(
-        vertex: Vertex,
-        r: &str,
-        g: &str,
-        b: &str,
-        disp: &glium::Display<WindowSurface>,
-    ) -> (Box<dyn Part>, Box<dyn Part>) {
-        let claw1_vertices = vec![
-            Vertex {
-                position: [
-                    // root
-                    vertex.position[0],
-                    vertex.position[1] + DEF_THINNING + 0.01,
-                ],
-            },
-            Vertex {
-                position: [
-                    // middle
-                    vertex.position[0] + 0.01,
-                    vertex.position[1] + DEF_THINNING + 0.04,
-                ],
-            },
-            Vertex {
-                position: [
-                    // tip
-                    vertex.position[0] + 0.06,
-                    vertex.position[1] + DEF_THINNING + 0.03,
-                ],
-            },
-        ];
-
-        let claw2_vertices = vec![
-            Vertex {
-                position: [
-                    // root
-                    vertex.position[0],
-                    vertex.position[1] - DEF_THINNING - 0.01,
-                ],
-            },
-            Vertex {
-                position: [
-                    // middle
-                    vertex.position[0] + 0.01,
-                    vertex.position[1] - DEF_THINNING - 0.04,
-                ],
-            },
-            Vertex {
-                position: [
-                    // tip
-                    vertex.position[0] + 0.06,
-                    vertex.position[1] - DEF_THINNING - 0.03,
-                ],
-            },
-        ];
-
-        let indices = (0..=2).collect();
-        let (vertex_buffer1, index_buffer1) =
-            generate_vertex_index_buffer(disp, &claw1_vertices, &indices);
-        let (vertex_buffer2, index_buffer2) =
-            generate_vertex_index_buffer(disp, &claw2_vertices, &indices);
-        let program1 = generate_program(r, g, b, disp);
-        let program2 = generate_program(r, g, b, disp);
-        let tip1 = claw1_vertices[0];
-        let tip2 = claw2_vertices[0];
-        let claw1 = Claw {
-            vertices: claw1_vertices,
-            tip: tip1,
-            vertex_buffer: vertex_buffer1,
-            index_buffer: index_buffer1,
-            program: program1,
-        };
+pub struct Object {
+    pub vertices: Vec<Vec2>,
+    pub color: Color,
+    pub mass: f32,
+}
 
-        let claw2 = Claw {
-            vertices: claw2_vertices,
-            tip: tip2,
-            vertex_buffer: vertex_buffer2,
-            index_buffer: index_buffer2,
-            program: program2,
-        };
-        (Box::new(claw1), Box::new(claw2))
-    }
-
-    pub fn generate_chain(
-        center_x: f32,
-        center_y: f32,
-        r: &str,
-        g: &str,
-        b: &str,
-        disp: &glium::Display<WindowSurface>,
-    ) -> Chain {
-        let (vertices, indices, tip) = generate_vertices(center_x, center_y);
-
-        let (vertex_buffer, index_buffer) = generate_vertex_index_buffer(disp, &vertices, &indices);
-        let program = generate_program(r, g, b, disp);
-        Chain {
-            vertices,
-            indices,
-            tip,
-            vertex_buffer,
-            index_buffer,
-            program,
-        }
+impl Part for Object {
+    fn get_vertices(&mut self) -> &mut Vec<Vec2> {
+        &mut self.vertices
     }
+    fn get_vertices_ref(&self) -> &Vec<Vec2> {
+        &self.vertices
+    }
+    fn get_tip(&self) -> Option<Vec2> {
+        None
+    }
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_mass(&self) -> f32 {
+        self.mass
+    }
+}
 
-    fn generate_vertices(center_x: f32, center_y: f32) -> (Vec<Vertex>, Vec<u32>, Vertex) {
-        let mut vertices = vec![
-            Vertex {
-                position: [center_x + DEF_RADIUS - DEF_THINNING, center_y + DEF_HEIGHT], // top right
-            },
-            Vertex {
-                position: [center_x - DEF_RADIUS, center_y], // bottom left
-            },
-            Vertex {
-                position: [center_x - DEF_RADIUS + DEF_THINNING, center_y + DEF_HEIGHT], // top left
-            },
-        ];
-        let mut indices: Vec<u32> = (0..=vertices.len() as u32).collect();
-
-        // generate vertices and indices for circle
-        let circle_segments = 100;
-        for i in 3..=circle_segments {
-            let theta = 2.0 * PI * (i as f32) / (circle_segments as f32);
-            let x = DEF_RADIUS * theta.cos();
-            let y = DEF_RADIUS * theta.sin();
-            vertices.push(Vertex {
-                position: [x + center_x, y + center_y],
-            });
-            indices.push(i as u32);
-        }
-
-        let tip = Vertex {
-            position: [center_x, center_y + DEF_HEIGHT],
-        };
+// immovable part of the workcell, e.g. a table or a wall
+pub struct Fixture {
+    pub vertices: Vec<Vec2>,
+    pub color: Color,
+    // convex pieces of the shape, fixtures never move so they are split once
+    pieces: Vec<Vec<Vec2>>,
+}
 
-        (vertices, indices, tip)
+impl Fixture {
+    pub fn pieces(&self) -> &[Vec<Vec2>] {
+        &self.pieces
     }
+}
 
-    fn generate_vertex_index_buffer(
-        disp: &glium::Display<WindowSurface>,
-        vertexbuf: &Vec<Vertex>,
-        indexbuf: &Vec<u32>,
-    ) -> (glium::VertexBuffer<Vertex>, glium::IndexBuffer<u32>) {
-        (
-            glium::VertexBuffer::new(disp, vertexbuf).unwrap(),
-            glium::IndexBuffer::new(disp, glium::index::PrimitiveType::TriangleFan, indexbuf)
-                .unwrap(),
-        )
+impl Part for Fixture {
+    fn get_vertices(&mut self) -> &mut Vec<Vec2> {
+        &mut self.vertices
+    }
+    fn get_vertices_ref(&self) -> &Vec<Vec2> {
+        &self.vertices
+    }
+    fn get_tip(&self) -> Option<Vec2> {
+        None
+    }
+    fn get_color(&self) -> Color {
+        self.color
     }
+}
 
-    fn generate_program(
-        r: &str,
-        g: &str,
-        b: &str,
-        disp: &glium::Display<WindowSurface>,
-    ) -> glium::program::Program {
-        let vertex_shader_src = r#"
-        #version 330 core
+pub fn generate_object(vertices: Vec<Vec2>, color: Color, mass: f32) -> Object {
+    Object {
+        vertices,
+        color,
+        mass,
+    }
+}
 
-        in vec2 position;
+pub fn generate_fixture(vertices: Vec<Vec2>, color: Color) -> Fixture {
+    Fixture {
+        pieces: convex_pieces(&vertices),
+        vertices,
+        color,
+    }
+}
 
-        void main() {
-            gl_Position = vec4(position, 0.0, 1.0);
-        }
-    "#;
+pub fn generate_random_object(bl: (f32, f32), color: Color) -> Object {
+    let vertices = vec![
+        Vec2::new(bl.0, bl.1),              //bl
+        Vec2::new(bl.0 + 0.05, bl.1),       //br
+        Vec2::new(bl.0 + 0.05, bl.1 + 0.1), //tr
+        Vec2::new(bl.0, bl.1 + 0.1),        //tl
+    ];
+    generate_object(vertices, color, DEF_OBJECT_MASS)
+}
 
-        let fragment_shader_src = format!(
-            r#"
-        #version 330 core
+// open claws in the wrist frame, the root of each claw is its rotation point
+fn generate_claws(claw: &ClawDescription, half_width: f32) -> (Vec<Vec2>, Vec<Vec2>) {
+    let (length, depth) = (claw.length, claw.depth);
+    let claw1_vertices = vec![
+        Vec2::new(0.0, half_width),                        // root
+        Vec2::new(length / 6.0, half_width + depth),       // middle
+        Vec2::new(length, half_width + depth * 2.0 / 3.0), // tip
+    ];
+
+    let claw2_vertices = vec![
+        Vec2::new(0.0, -half_width),                        // root
+        Vec2::new(length / 6.0, -half_width - depth),       // middle
+        Vec2::new(length, -half_width - depth * 2.0 / 3.0), // tip
+    ];
+
+    (claw1_vertices, claw2_vertices)
+}
 
-        out vec4 color;
+// link and joint in the link frame, the link points along the x axis
+fn generate_vertices(link: &LinkDescription) -> Vec<Vec2> {
+    let mut vertices = vec![
+        Vec2::new(link.length, -link.width / 2.0), // top right
+        Vec2::new(0.0, link.joint_radius),         // bottom left
+        Vec2::new(link.length, link.width / 2.0),  // top left
+    ];
+
+    // generate vertices for circle
+    let circle_segments = 100;
+    for i in 3..=circle_segments {
+        let theta = 2.0 * PI * (i as f32) / (circle_segments as f32) - PI / 2.0;
+        vertices.push(Vec2::new(
+            link.joint_radius * theta.cos(),
+            link.joint_radius * theta.sin(),
+        ));
+    }
 
-        void main() {{
-            color = vec4({}, {}, {}, 1.0);
-        }}
-    "#,
-            r, g, b
-        );
+    vertices
+}
 
-        glium::Program::from_source(disp, vertex_shader_src, &fragment_shader_src, None).unwrap()
-    }
-
-    pub fn rotate(
-        angle: f32,
-        part: &mut dyn Part,
-        disp: &glium::Display<WindowSurface>,
-        center_x: f32,
-        center_y: f32,
-    ) -> glium::VertexBuffer<Vertex> {
-        let rotation_angle = angle.to_radians();
-        let rotation_matrix = [
-            [rotation_angle.cos(), -rotation_angle.sin()],
-            [rotation_angle.sin(), rotation_angle.cos()],
-        ];
-        for vertex in &mut *part.get_vertices() {
-            let x = vertex.position[0] - center_x;
-            let y = vertex.position[1] - center_y;
-
-            vertex.position[0] = rotation_matrix[0][0] * x + rotation_matrix[0][1] * y + center_x;
-            vertex.position[1] = rotation_matrix[1][0] * x + rotation_matrix[1][1] * y + center_y;
-        }
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkDescription {
+    pub length: f32,
+    // width at the tip, the link widens towards its joint circle
+    pub width: f32,
+    pub joint_radius: f32,
+    pub color: Color,
+    // joint range in degrees
+    pub limits: (f32, f32),
+    // joint angle at start, the arm returns here with `B`
+    pub rest: f32,
+    // degrees per second
+    pub max_velocity: f32,
+    // degrees per second squared
+    pub max_acceleration: f32,
+    // degrees per second cubed, only limits S-curve profiles
+    pub max_jerk: f32,
+    // kilograms
+    pub mass: f32,
+    // distance of the center of mass from the joint along the link
+    pub center_of_mass: f32,
+    // moment of inertia about the center of mass
+    pub inertia: f32,
+    // largest torque of the joint in newton meters
+    pub max_torque: f32,
+    // controller and motor of the joint
+    pub servo: ServoDescription,
+    // degrees per jog key press
+    pub jog_increment: f32,
+}
 
-        // modify tip of the chain
-        let tip = part.get_tip();
-        match tip {
-            Some(vertex) => {
-                let x = vertex.position[0] - center_x;
-                let y = vertex.position[1] - center_y;
-                vertex.position[0] =
-                    rotation_matrix[0][0] * x + rotation_matrix[0][1] * y + center_x;
-                vertex.position[1] =
-                    rotation_matrix[1][0] * x + rotation_matrix[1][1] * y + center_y;
-            }
-            None => {}
+impl LinkDescription {
+    pub fn kinematic_limits(&self) -> KinematicLimits {
+        KinematicLimits {
+            velocity: self.max_velocity,
+            acceleration: self.max_acceleration,
+            jerk: self.max_jerk,
         }
-
-        glium::VertexBuffer::new(disp, &part.get_vertices()).unwrap()
     }
+}
 
-    pub fn rotate_all(
-        angle: f32,
-        parts: &mut HashMap<String, Box<dyn Part>>,
-        disp: &glium::Display<WindowSurface>,
-        center_x: f32,
-        center_y: f32,
-    ) {
-        for (_, part) in parts.iter_mut() {
-            let vertexbuf = rotate(angle, part.as_mut(), disp, center_x, center_y);
-            part.set_vertex_buf(vertexbuf);
-        }
-    }
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClawDescription {
+    // distance from the wrist to the claw tips, the tool center point lies there
+    pub length: f32,
+    // how far each claw bulges out to the side
+    pub depth: f32,
+    // opening angle in degrees of the fully open claws
+    pub opening: f32,
+    pub color: Color,
+    // degrees per second
+    pub max_velocity: f32,
+    // degrees per key press
+    pub jog_increment: f32,
+    // newtons each claw presses on a grasped object
+    pub grip_force: f32,
+    // friction coefficient between the claws and objects
+    pub friction: f32,
+}
 
-    pub fn execute(
-        lr1: (&mut i32, &mut i32),
-        lr2: (&mut i32, &mut i32),
-        lr3: (&mut i32, &mut i32),
-        lr4: (&mut i32, &mut i32),
-        _state: &mut (i32, i32, i32, i32),
-        r1: (f32, f32),
-        r2: (f32, f32),
-        r3: (f32, f32),
-        r4: (f32, f32),
-        r5: (f32, f32),
-        parts: &mut HashMap<String, Box<dyn Part>>,
-        disp: &glium::Display<WindowSurface>,
-        state: &mut State,
-        obj: &mut dyn Part,
-        _object: &mut i32,
-    ) {
-        match *_state {
-            (1, 0, 0, 0) => {
-                if *lr1.0 > state.l1 {
-                    rotate_all(3.0, parts, disp, r1.0, r1.1);
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(3.0, obj, disp, r1.0, r1.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr1.0 -= 1;
-                    *lr1.1 += 1;
-                } else if *lr1.0 < state.l1 {
-                    rotate_all(-3.0, parts, disp, r1.0, r1.1);
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(-3.0, obj, disp, r1.0, r1.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr1.0 += 1;
-                    *lr1.1 -= 1;
-                } else {
-                    *_state = (0, 1, 0, 0);
-                }
-                thread::sleep(SLEEP_DURATION);
-            }
-            (0, 1, 0, 0) => {
-                if *lr2.0 > state.l2 {
-                    let chain1 = parts.remove("chain1").unwrap();
-                    rotate_all(3.0, parts, disp, r2.0, r2.1);
-                    parts.insert("chain1".to_string(), chain1);
-
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(3.0, obj, disp, r2.0, r2.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr2.0 -= 1;
-                    *lr2.1 += 1;
-                } else if *lr2.0 < state.l2 {
-                    let chain1 = parts.remove("chain1").unwrap();
-                    rotate_all(-3.0, parts, disp, r2.0, r2.1);
-                    parts.insert("chain1".to_string(), chain1);
-
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(-3.0, obj, disp, r2.0, r2.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr2.0 += 1;
-                    *lr2.1 -= 1;
-                } else {
-                    *_state = (0, 0, 1, 0);
-                }
-                thread::sleep(SLEEP_DURATION);
-            }
-            (0, 0, 1, 0) => {
-                if *lr3.0 > state.l3 {
-                    let chain1 = parts.remove("chain1").unwrap();
-                    let chain2 = parts.remove("chain2").unwrap();
-                    rotate_all(3.0, parts, disp, r3.0, r3.1);
-                    parts.insert("chain1".to_string(), chain1);
-                    parts.insert("chain2".to_string(), chain2);
-
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(3.0, obj, disp, r3.0, r3.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr3.0 -= 1;
-                    *lr3.1 += 1;
-                } else if *lr3.0 < state.l3 {
-                    let chain1 = parts.remove("chain1").unwrap();
-                    let chain2 = parts.remove("chain2").unwrap();
-                    rotate_all(-3.0, parts, disp, r3.0, r3.1);
-                    parts.insert("chain1".to_string(), chain1);
-                    parts.insert("chain2".to_string(), chain2);
-
-                    if *_object == 1 {
-                        let obj_vertex_buf = rotate(-3.0, obj, disp, r3.0, r3.1);
-                        obj.set_vertex_buf(obj_vertex_buf);
-                    }
-
-                    *lr3.0 += 1;
-                    *lr3.1 -= 1;
-                } else {
-                    *_state = (0, 0, 0, 1);
-                }
-                thread::sleep(SLEEP_DURATION);
-            }
-            (0, 0, 0, 1) => {
-                if *lr4.0 > state.l4 {
-                    let claw1_vb = rotate(
-                        5.0,
-                        parts.get_mut("claw1").unwrap().as_mut(),
-                        disp,
-                        r4.0,
-                        r4.1,
-                    );
-                    let claw2_vb = rotate(
-                        -5.0,
-                        parts.get_mut("claw2").unwrap().as_mut(),
-                        disp,
-                        r5.0,
-                        r5.1,
-                    );
-                    parts.get_mut("claw1").unwrap().set_vertex_buf(claw1_vb);
-                    parts.get_mut("claw2").unwrap().set_vertex_buf(claw2_vb);
-
-                    if !detect_collision(
-                        parts.get("claw1").unwrap().as_ref(),
-                        parts.get("claw2").unwrap().as_ref(),
-                        &obj.get_vertices(),
-                    ) {
-                        *_object = 0;
-                    }
-
-                    *lr4.0 -= 1;
-                    *lr4.1 += 1;
-                } else if *lr4.0 < state.l4 {
-                    let claw1_vb = rotate(
-                        -5.0,
-                        parts.get_mut("claw1").unwrap().as_mut(),
-                        disp,
-                        r4.0,
-                        r4.1,
-                    );
-                    let claw2_vb = rotate(
-                        5.0,
-                        parts.get_mut("claw2").unwrap().as_mut(),
-                        disp,
-                        r5.0,
-                        r5.1,
-                    );
-                    parts.get_mut("claw1").unwrap().set_vertex_buf(claw1_vb);
-                    parts.get_mut("claw2").unwrap().set_vertex_buf(claw2_vb);
-
-                    if *_object == 0
-                        && detect_collision(
-                            parts.get("claw1").unwrap().as_ref(),
-                            parts.get("claw2").unwrap().as_ref(),
-                            &obj.get_vertices(),
-                        )
-                    {
-                        *_object = 1;
-                    }
-
-                    *lr4.0 += 1;
-                    *lr4.1 -= 1;
-                } else {
-                    *_state = (0, 0, 0, 0);
-                }
-                thread::sleep(SLEEP_DURATION);
-            }
-            _ => {}
+impl Default for ClawDescription {
+    fn default() -> Self {
+        ClawDescription {
+            length: DEF_CLAW_LENGTH,
+            depth: DEF_CLAW_DEPTH,
+            opening: DEF_CLAW_OPENING,
+            color: [1.0, 0.0, 0.0],
+            max_velocity: DEF_CLAW_VELOCITY,
+            jog_increment: DEF_CLAW_JOG_INCREMENT,
+            grip_force: DEF_GRIP_FORCE,
+            friction: DEF_GRIP_FRICTION,
         }
     }
+}
 
-    pub fn apply_gravity(
-        moved_object: &str,
-        state: Option<&mut State>,
-        objects: &mut HashMap<String, Box<dyn Part>>,
-        disp: &glium::Display<WindowSurface>,
-    ) {
-        let mut s = String::new();
-        if state.is_some() {
-            s.push_str(state.unwrap().moved_object.as_str());
-        }
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArmDescription {
+    pub base: Vec2,
+    // links from the base to the wrist, each one with a revolute joint at its root
+    pub links: Vec<LinkDescription>,
+    pub claw: ClawDescription,
+}
 
-        for (key, object) in objects.iter_mut() {
-            if object.get_vertices()[0].position[1] > GROUND && moved_object != *key && s != *key {
-                for vertex in object.get_vertices() {
-                    vertex.position[1] -= 0.01;
-                }
-                let buf = glium::VertexBuffer::new(disp, object.get_vertices()).unwrap();
-                object.set_vertex_buf(buf);
-            }
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectDescription {
+    pub name: String,
+    pub vertices: Vec<Vec2>,
+    pub color: Color,
+    pub mass: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixtureDescription {
+    pub name: String,
+    pub vertices: Vec<Vec2>,
+    pub color: Color,
+}
+
+// everything in the workcell around the arm
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellDescription {
+    pub ground: f32,
+    pub objects: Vec<ObjectDescription>,
+    pub fixtures: Vec<FixtureDescription>,
+}
+
+impl Default for CellDescription {
+    fn default() -> Self {
+        CellDescription {
+            ground: GROUND,
+            objects: vec![ObjectDescription {
+                name: "obj".to_string(),
+                vertices: vec![
+                    Vec2::new(0.15, -0.45), //bl
+                    Vec2::new(0.2, -0.45),  //br
+                    Vec2::new(0.2, -0.35),  //tr
+                    Vec2::new(0.15, -0.35), //tl
+                ],
+                color: [0.0, 0.0, 0.0],
+                mass: DEF_OBJECT_MASS,
+            }],
+            fixtures: vec![
+                FixtureDescription {
+                    name: "table".to_string(),
+                    vertices: vec![
+                        Vec2::new(0.3, -0.43),
+                        Vec2::new(0.33, -0.43),
+                        Vec2::new(0.33, -0.25),
+                        Vec2::new(0.57, -0.25),
+                        Vec2::new(0.57, -0.43),
+                        Vec2::new(0.6, -0.43),
+                        Vec2::new(0.6, -0.22),
+                        Vec2::new(0.3, -0.22),
+                    ],
+                    color: DEF_FIXTURE_COLOR,
+                },
+                FixtureDescription {
+                    name: "wall".to_string(),
+                    vertices: vec![
+                        Vec2::new(0.9, -0.43),
+                        Vec2::new(0.95, -0.43),
+                        Vec2::new(0.95, 0.6),
+                        Vec2::new(0.9, 0.6),
+                    ],
+                    color: DEF_FIXTURE_COLOR,
+                },
+            ],
         }
     }
+}
 
-    fn check_boundaries(ray_start: Vertex, first: Vertex, second: Vertex) -> bool {
-        let (x, y) = (ray_start.position[0], ray_start.position[1]);
-        let (x1, y1) = (first.position[0], first.position[1]);
-        let (x2, y2) = (second.position[0], second.position[1]);
+impl Default for ArmDescription {
+    fn default() -> Self {
+        let link = |color, limits, rest| LinkDescription {
+            length: DEF_HEIGHT,
+            width: 2.0 * (DEF_RADIUS - DEF_THINNING),
+            joint_radius: DEF_RADIUS,
+            color,
+            limits,
+            rest,
+            max_velocity: DEF_JOINT_VELOCITY,
+            max_acceleration: DEF_JOINT_ACCELERATION,
+            max_jerk: DEF_JOINT_JERK,
+            // slender rod
+            mass: DEF_LINK_MASS,
+            center_of_mass: DEF_HEIGHT / 2.0,
+            inertia: DEF_LINK_MASS * DEF_HEIGHT * DEF_HEIGHT / 12.0,
+            max_torque: DEF_JOINT_TORQUE,
+            servo: ServoDescription::default(),
+            jog_increment: DEF_JOG_INCREMENT,
+        };
+        ArmDescription {
+            base: BASE,
+            links: vec![
+                link([1.0, 0.6, 0.0], (-90.0, 0.0), 0.0),
+                link([0.0, 1.0, 0.0], (-180.0, 0.0), 0.0),
+                link([0.0, 0.0, 1.0], (-162.0, 0.0), -90.0),
+            ],
+            claw: ClawDescription::default(),
+        }
+    }
+}
 
-        if ((y1 <= y && y < y2) || (y2 <= y && y < y1)) && x < f32::max(x1, x2) {
-            return true;
+impl ArmDescription {
+    pub fn rest(&self) -> JointState {
+        JointState {
+            angles: self.links.iter().map(|link| link.rest).collect(),
+            claw: self.claw.opening,
         }
-        false
     }
 
-    fn ray_edge_intersect(ray_start: Vertex, first: Vertex, second: Vertex) -> f32 {
-        let (x, y) = (ray_start.position[0], ray_start.position[1]);
-        let (x1, y1) = (first.position[0], first.position[1]);
-        let (x2, y2) = (second.position[0], second.position[1]);
+    pub fn claw_limits(&self) -> (f32, f32) {
+        (0.0, self.claw.opening)
+    }
 
-        let scaling_factor = ((x1 - x) * (y2 - y1) - (y1 - y) * (x2 - x1)) / (y2 - y1);
+    pub fn limits(&self) -> Vec<(f32, f32)> {
+        self.links.iter().map(|link| link.limits).collect()
+    }
 
-        x + scaling_factor + 0.07
+    pub fn kinematic_limits(&self) -> Vec<KinematicLimits> {
+        self.links
+            .iter()
+            .map(LinkDescription::kinematic_limits)
+            .collect()
     }
+}
 
-    fn detect_collision(claw1: &dyn Part, claw2: &dyn Part, vertices: &Vec<Vertex>) -> bool {
-        let mut res = false;
-        let length = vertices.len();
+pub struct Arm {
+    pub description: ArmDescription,
+    pub joints: JointState,
+    pub chains: Vec<Chain>,
+    pub claws: [Claw; 2],
+    link_shapes: Vec<Vec<Vec2>>,
+    // convex pieces of the link shapes for collision checks
+    link_pieces: Vec<Vec<Vec<Vec2>>>,
+    claw_shapes: [Vec<Vec2>; 2],
+}
 
-        // number of intersections
-        let (mut num_intersec_right, mut num_intersec_left) = (0, 0);
-        for i in 0..length {
-            // get all the edges in a cyclic manner
-            let first = vertices[i];
-            let second = vertices[(i + 1) % length];
+// part of the arm, counted from the base
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArmPart {
+    Link(usize),
+    Claw(usize),
+}
 
-            if check_boundaries(claw1.get_vertices_ref()[2], first, second)
-                && check_boundaries(claw2.get_vertices_ref()[2], first, second)
-            {
-                let intersec_right = ray_edge_intersect(claw1.get_vertices_ref()[2], first, second);
-                let intersec_left = ray_edge_intersect(claw2.get_vertices_ref()[2], first, second);
+impl fmt::Display for ArmPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArmPart::Link(i) => write!(f, "link {}", i + 1),
+            ArmPart::Claw(i) => write!(f, "claw {}", i + 1),
+        }
+    }
+}
 
-                if claw1.get_vertices_ref()[2].position[0] < intersec_right {
-                    num_intersec_right += 1;
-                }
+impl Arm {
+    pub fn new(description: ArmDescription) -> Arm {
+        let claw = || Claw {
+            vertices: Vec::new(),
+            tip: Vec2::ZERO,
+            color: description.claw.color,
+        };
+        let half_width = description
+            .links
+            .last()
+            .map_or(DEF_RADIUS, |link| link.width / 2.0);
+        let (claw1, claw2) = generate_claws(&description.claw, half_width);
+        let mut arm = Arm {
+            joints: description.rest(),
+            chains: description
+                .links
+                .iter()
+                .map(|link| Chain {
+                    vertices: Vec::new(),
+                    tip: Vec2::ZERO,
+                    color: link.color,
+                })
+                .collect(),
+            claws: [claw(), claw()],
+            link_shapes: description.links.iter().map(generate_vertices).collect(),
+            link_pieces: Vec::new(),
+            claw_shapes: [claw1, claw2],
+            description,
+        };
+        arm.link_pieces = arm
+            .link_shapes
+            .iter()
+            .map(|shape| convex_pieces(shape))
+            .collect();
+        arm.update();
+        arm
+    }
 
-                if claw2.get_vertices_ref()[2].position[0] < intersec_left {
-                    num_intersec_left += 1;
-                }
-            }
+    // recompute link and claw geometry from the joint angles
+    pub fn update(&mut self) {
+        let frames = forward_kinematics(&self.description, &self.joints.angles);
+        for (((chain, shape), frame), link) in self
+            .chains
+            .iter_mut()
+            .zip(self.link_shapes.iter())
+            .zip(frames.iter())
+            .zip(self.description.links.iter())
+        {
+            chain.vertices = shape.iter().map(|v| frame.transform(*v)).collect();
+            chain.tip = frame.transform(Vec2::new(link.length, 0.0));
+        }
+
+        let wrist = self.wrist();
+        let closing = self.description.claw.opening - self.joints.claw;
+        for ((claw, shape), sign) in self
+            .claws
+            .iter_mut()
+            .zip(self.claw_shapes.iter())
+            .zip([-1.0, 1.0])
+        {
+            let root = shape[0];
+            claw.vertices = shape
+                .iter()
+                .map(|v| wrist.transform(v.rotated_about(root, sign * closing)))
+                .collect();
+            claw.tip = claw.vertices[0];
         }
+    }
 
-        if num_intersec_left % 2 == 1 && num_intersec_right % 2 == 1 {
-            res = true;
-        }
+    pub fn parts(&self) -> impl Iterator<Item = &dyn Part> {
+        self.chains
+            .iter()
+            .map(|chain| chain as &dyn Part)
+            .chain(self.claws.iter().map(|claw| claw as &dyn Part))
+    }
 
-        res
+    pub fn wrist(&self) -> Frame {
+        wrist(&self.description, &self.joints.angles)
     }
 
-    pub fn detect_collisions(
-        claw1: &dyn Part,
-        claw2: &dyn Part,
-        objects: &HashMap<String, Box<dyn Part>>,
-    ) -> Option<String> {
-        for (key, object) in objects.iter() {
-            if detect_collision(claw1, claw2, object.get_vertices_ref()) {
-                return Some(String::from(key));
+    // every part as convex pieces at the current pose
+    pub fn collision_shapes(&self) -> Vec<(ArmPart, Vec<Vec<Vec2>>)> {
+        let frames = forward_kinematics(&self.description, &self.joints.angles);
+        let links =
+            self.link_pieces
+                .iter()
+                .zip(frames.iter())
+                .enumerate()
+                .map(|(i, (pieces, frame))| {
+                    let pieces = pieces
+                        .iter()
+                        .map(|piece| piece.iter().map(|v| frame.transform(*v)).collect())
+                        .collect();
+                    (ArmPart::Link(i), pieces)
+                });
+        let claws = self
+            .claws
+            .iter()
+            .enumerate()
+            .map(|(i, claw)| (ArmPart::Claw(i), vec![claw.vertices.clone()]));
+        links.chain(claws).collect()
+    }
+
+    // first two parts that overlap although no joint connects them
+    pub fn self_collision(&self) -> Option<(ArmPart, ArmPart)> {
+        let shapes = self.collision_shapes();
+        let last = self.chains.len().saturating_sub(1);
+        let joined = |a: ArmPart, b: ArmPart| match (a, b) {
+            (ArmPart::Link(i), ArmPart::Link(j)) => i.abs_diff(j) <= 1,
+            (ArmPart::Link(i), ArmPart::Claw(_)) | (ArmPart::Claw(_), ArmPart::Link(i)) => {
+                i == last
+            }
+            (ArmPart::Claw(_), ArmPart::Claw(_)) => true,
+        };
+        for (i, (first, pieces)) in shapes.iter().enumerate() {
+            for (second, others) in shapes.iter().skip(i + 1) {
+                if !joined(*first, *second)
+                    && pieces.iter().any(|piece| {
+                        others
+                            .iter()
+                            .any(|other| convex_contact(piece, other).is_some())
+                    })
+                {
+                    return Some((*first, *second));
+                }
             }
         }
         None
     }
 
-    pub fn create(
-        display: &glium::Display<WindowSurface>,
-    ) -> (
-        HashMap<String, Box<dyn Part>>,
-        HashMap<String, Box<dyn Part>>,
-        Object,
-    ) {
-        let mut chain1: Box<dyn Part> =
-            Box::new(generate_chain(-0.5, -0.4, "1.0", "0.6", "0.0", display));
-        let mut chain2: Box<dyn Part> = Box::new(generate_chain(
-            chain1.get_tip().unwrap().position[0],
-            chain1.get_tip().unwrap().position[1],
-            "0.0",
-            "1.0",
-            "0.0",
-            display,
-        ));
-        let mut chain3: Box<dyn Part> = Box::new(generate_chain(
-            chain2.get_tip().unwrap().position[0],
-            chain2.get_tip().unwrap().position[1],
-            "0.0",
-            "0.0",
-            "1.0",
-            display,
-        ));
-        let chain3_buf = rotate(
-            -90.0,
-            chain3.as_mut(),
-            display,
-            chain2.get_tip().unwrap().position[0],
-            chain2.get_tip().unwrap().position[1],
-        );
-
-        chain3.set_vertex_buf(chain3_buf);
-
-        let vertex1 = Vertex {
-            position: [0.15, -0.45], //bl
-        };
-        let vertex2 = Vertex {
-            position: [0.2, -0.45], //br
-        };
-        let vertex3 = Vertex {
-            position: [0.2, -0.35], //tr
-        };
-        let vertex4 = Vertex {
-            position: [0.15, -0.35], //tl
-        };
-
-        let vertices = vec![vertex1, vertex2, vertex3, vertex4];
-
-        let obj: Box<dyn Part> = Box::new(generate_object(vertices, "0.0", "0.0", "0.0", display));
-
-        let (claw1, claw2) =
-            generate_claws(*chain3.get_tip().unwrap(), "1.0", "0.0", "0.0", display);
-        let mut parts: HashMap<String, Box<dyn Part>> = HashMap::new();
-        parts.insert("chain1".to_string(), chain1);
-        parts.insert("chain2".to_string(), chain2);
-        parts.insert("chain3".to_string(), chain3);
-        parts.insert("claw1".to_string(), claw1);
-        parts.insert("claw2".to_string(), claw2);
-
-        let mut objects: HashMap<String, Box<dyn Part>> = HashMap::new();
-        objects.insert("obj".to_string(), obj);
-
-        let dummy = generate_object(Vec::new(), "1.0", "1.0", "1.0", display);
+    pub fn tcp(&self) -> Frame {
+        tcp_pose(&self.description, &self.joints.angles)
+    }
+}
 
-        (parts, objects, dummy)
+pub type Parts = HashMap<String, Box<dyn Part>>;
+pub type Fixtures = HashMap<String, Fixture>;
+
+// the arm, the objects and the fixtures of a workcell
+pub fn create(description: ArmDescription, cell: &CellDescription) -> (Arm, Parts, Fixtures) {
+    let arm = Arm::new(description);
+
+    let mut objects: Parts = HashMap::new();
+    for object in cell.objects.iter() {
+        objects.insert(
+            object.name.clone(),
+            Box::new(generate_object(
+                object.vertices.clone(),
+                object.color,
+                object.mass,
+            )),
+        );
+    }
+    let mut fixtures: Fixtures = HashMap::new();
+    for fixture in cell.fixtures.iter() {
+        fixtures.insert(
+            fixture.name.clone(),
+            generate_fixture(fixture.vertices.clone(), fixture.color),
+        );
     }
+
+    (arm, objects, fixtures)
 }
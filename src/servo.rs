@@ -0,0 +1,131 @@
+// defaults of a geared DC motor joint, SI units
+pub const DEF_KP: f32 = 100.0;
+pub const DEF_KI: f32 = 200.0;
+pub const DEF_KD: f32 = 2.0;
+pub const DEF_GEAR_RATIO: f32 = 100.0;
+pub const DEF_TORQUE_CONSTANT: f32 = 0.05;
+pub const DEF_RESISTANCE: f32 = 1.0;
+pub const DEF_VOLTAGE: f32 = 24.0;
+pub const DEF_FRICTION: f32 = 0.5;
+
+// PID controller and DC motor of a joint, the controller output is the motor voltage
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ServoDescription {
+    // volts per radian of error, per radian second and per radian per second
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    // motor turns per joint turn
+    pub gear_ratio: f32,
+    // newton meters per ampere at the motor, also the back-EMF constant in volt seconds
+    pub torque_constant: f32,
+    // ohms of the winding
+    pub resistance: f32,
+    // supply voltage, the controller output is clamped to it
+    pub voltage: f32,
+    // viscous joint friction in newton meter seconds per radian
+    pub friction: f32,
+}
+
+impl Default for ServoDescription {
+    fn default() -> Self {
+        ServoDescription {
+            kp: DEF_KP,
+            ki: DEF_KI,
+            kd: DEF_KD,
+            gear_ratio: DEF_GEAR_RATIO,
+            torque_constant: DEF_TORQUE_CONSTANT,
+            resistance: DEF_RESISTANCE,
+            voltage: DEF_VOLTAGE,
+            friction: DEF_FRICTION,
+        }
+    }
+}
+
+// controller state of a joint
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Servo {
+    integral: f32,
+    // last motor voltage and current, for tuning
+    pub voltage: f32,
+    pub current: f32,
+}
+
+impl Servo {
+    // joint torque in newton meters for the angle error and error rate in degrees (per second)
+    // at the joint velocity in degrees per second
+    pub fn torque(
+        &mut self,
+        servo: &ServoDescription,
+        error: f32,
+        error_rate: f32,
+        velocity: f32,
+        dt: f32,
+    ) -> f32 {
+        let (error, error_rate, velocity) = (
+            error.to_radians(),
+            error_rate.to_radians(),
+            velocity.to_radians(),
+        );
+        let output =
+            servo.kp * error + servo.ki * (self.integral + error * dt) + servo.kd * error_rate;
+        self.voltage = output.clamp(-servo.voltage, servo.voltage);
+        // no integration while the output is saturated, the integral would wind up
+        if output == self.voltage {
+            self.integral += error * dt;
+        }
+
+        let back_emf = servo.torque_constant * servo.gear_ratio * velocity;
+        self.current = (self.voltage - back_emf) / servo.resistance;
+        servo.gear_ratio * servo.torque_constant * self.current - servo.friction * velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 1000.0;
+    // joint inertia in kilogram square meters
+    const INERTIA: f32 = 0.05;
+
+    // step the joint from rest at zero to `target` degrees, the angles of each step
+    fn step_response(servo: &mut Servo, target: f32, seconds: f32) -> Vec<f32> {
+        let description = ServoDescription::default();
+        let (mut angle, mut velocity) = (0.0_f32, 0.0_f32);
+        let mut angles = Vec::new();
+        for _ in 0..(seconds / DT) as usize {
+            let torque = servo.torque(&description, target - angle, -velocity, velocity, DT);
+            velocity += (torque / INERTIA).to_degrees() * DT;
+            angle += velocity * DT;
+            angles.push(angle);
+        }
+        angles
+    }
+
+    #[test]
+    fn reaches_a_step_setpoint() {
+        let mut servo = Servo::default();
+        let angles = step_response(&mut servo, 10.0, 3.0);
+        let settled = &angles[angles.len() * 2 / 3..];
+        assert!(settled.iter().all(|angle| (angle - 10.0).abs() < 0.05));
+        assert!(servo.voltage.abs() < 1.0);
+    }
+
+    #[test]
+    fn does_not_wind_up_while_saturated() {
+        let description = ServoDescription::default();
+        let mut servo = Servo::default();
+        // far from the setpoint the output stays at the supply voltage
+        for _ in 0..100 {
+            servo.torque(&description, 90.0, 0.0, 0.0, DT);
+            assert_eq!(servo.voltage, description.voltage);
+        }
+        assert_eq!(servo.integral, 0.0);
+
+        // so a large step does not overshoot far past the setpoint
+        let angles = step_response(&mut Servo::default(), 90.0, 2.0);
+        let overshoot = angles.iter().fold(f32::MIN, |a, b| a.max(*b)) - 90.0;
+        assert!(overshoot < 0.05 * 90.0);
+    }
+}
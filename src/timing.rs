@@ -0,0 +1,355 @@
+use crate::motion::{Motion, MotionError};
+use crate::profile::KinematicLimits;
+
+// iterations of the bisection for the largest velocity that can still brake in time
+const BISECTION_STEPS: usize = 40;
+// bound of the squared path speed where no joint moves, in points per second squared
+const MAX_PATH_SPEED: f32 = 1.0e8;
+
+// inverse dynamics of the arm, used to keep joint torques within limits
+pub trait Dynamics {
+    // joint torques for angles in degrees, velocities in degrees per second and accelerations
+    // in degrees per second squared
+    fn torques(&self, angles: &[f32], velocities: &[f32], accelerations: &[f32]) -> Vec<f32>;
+    // largest torque each joint can apply
+    fn torque_limits(&self) -> Vec<f32>;
+}
+
+// `lower <= a * s'' + b * s'^2 + c <= upper` at a path point, s is the path parameter
+#[derive(Copy, Clone, Debug)]
+struct Constraint {
+    a: f32,
+    b: f32,
+    c: f32,
+    lower: f32,
+    upper: f32,
+}
+
+// path point with its constraints
+struct Point {
+    constraints: Vec<Constraint>,
+    // bound of s'^2 from the joint velocities
+    max_speed: f32,
+}
+
+impl Point {
+    // range of s'' that satisfies all constraints at the squared path speed `x`
+    fn acceleration_range(&self, x: f32) -> Option<(f32, f32)> {
+        let (mut min, mut max) = (f32::NEG_INFINITY, f32::INFINITY);
+        for c in self.constraints.iter() {
+            let (lower, upper) = (c.lower - c.b * x - c.c, c.upper - c.b * x - c.c);
+            if c.a.abs() < 1e-9 {
+                if lower > 0.0 || upper < 0.0 {
+                    return None;
+                }
+            } else if c.a > 0.0 {
+                min = min.max(lower / c.a);
+                max = max.min(upper / c.a);
+            } else {
+                min = min.max(upper / c.a);
+                max = max.min(lower / c.a);
+            }
+        }
+        (min <= max).then_some((min, max))
+    }
+}
+
+// path retimed to follow the fastest velocity profile within the limits
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedPath {
+    points: Vec<Vec<f32>>,
+    // time at each point
+    times: Vec<f32>,
+    // path speed in points per second at each point
+    speeds: Vec<f32>,
+}
+
+impl TimedPath {
+    // time the path takes, e.g. for cycle time studies
+    pub fn cycle_time(&self) -> f32 {
+        self.times.last().copied().unwrap_or_default()
+    }
+}
+
+impl Motion for TimedPath {
+    fn duration(&self) -> f32 {
+        self.cycle_time()
+    }
+
+    fn sample(&self, t: f32) -> Vec<f32> {
+        let last = self.points.len() - 1;
+        if t >= self.cycle_time() || last == 0 {
+            return self.points[last].clone();
+        }
+        let i = self.times.partition_point(|time| *time <= t).clamp(1, last) - 1;
+        // constant path acceleration between two points
+        let dt = self.times[i + 1] - self.times[i];
+        let u = (self.speeds[i + 1] - self.speeds[i]) / dt;
+        let tau = (t - self.times[i]).max(0.0);
+        let f = (self.speeds[i] * tau + u * tau * tau / 2.0).clamp(0.0, 1.0);
+        self.points[i]
+            .iter()
+            .zip(self.points[i + 1].iter())
+            .map(|(a, b)| a + (b - a) * f)
+            .collect()
+    }
+}
+
+// time-optimal parameterization of the joint path through `points`, the arm starts and ends at
+// rest, joint velocities and accelerations stay within `limits` and joint torques within the
+// limits of `dynamics`
+pub fn time_optimal(
+    points: &[Vec<f32>],
+    limits: &[KinematicLimits],
+    dynamics: Option<&dyn Dynamics>,
+) -> Result<TimedPath, MotionError> {
+    let n = points.len().saturating_sub(1);
+    if n == 0 {
+        return Ok(TimedPath {
+            points: points.to_vec(),
+            times: vec![0.0; points.len()],
+            speeds: vec![0.0; points.len()],
+        });
+    }
+
+    // the path parameter s runs through the point indices, derivatives by finite differences
+    let derivative = |k: usize| -> Vec<f32> {
+        let (before, after) = (k.saturating_sub(1), (k + 1).min(n));
+        difference(&points[before], &points[after], (after - before) as f32)
+    };
+    let curvature = |k: usize| -> Vec<f32> {
+        let k = k.clamp(1, (n - 1).max(1));
+        let (before, after) = (k - 1, (k + 1).min(n));
+        points[before]
+            .iter()
+            .zip(points[k].iter().zip(points[after].iter()))
+            .map(|(a, (b, c))| a - 2.0 * b + c)
+            .collect()
+    };
+    let torque_limits = dynamics.map(|dynamics| dynamics.torque_limits());
+
+    let path: Vec<Point> = (0..=n)
+        .map(|k| {
+            let (dq, ddq) = (derivative(k), curvature(k));
+            let mut constraints = Vec::new();
+            let mut max_speed = f32::INFINITY;
+            for ((dq, ddq), limits) in dq.iter().zip(ddq.iter()).zip(limits.iter()) {
+                if dq.abs() > 1e-9 {
+                    max_speed = max_speed.min((limits.velocity / dq).powi(2));
+                }
+                constraints.push(Constraint {
+                    a: *dq,
+                    b: *ddq,
+                    c: 0.0,
+                    lower: -limits.acceleration,
+                    upper: limits.acceleration,
+                });
+            }
+            if let (Some(dynamics), Some(torque_limits)) = (dynamics, torque_limits.as_ref()) {
+                // evaluated at the fastest path speed, at one point per second the velocity terms
+                // would be lost against gravity
+                let x = if max_speed.is_finite() {
+                    max_speed.max(1.0)
+                } else {
+                    1.0
+                };
+                let scaled = |values: &[f32], factor: f32| -> Vec<f32> {
+                    values.iter().map(|value| value * factor).collect()
+                };
+                let zero = vec![0.0; dq.len()];
+                let gravity = dynamics.torques(&points[k], &zero, &zero);
+                let inertia = dynamics.torques(&points[k], &zero, &scaled(&dq, x));
+                let coupling =
+                    dynamics.torques(&points[k], &scaled(&dq, x.sqrt()), &scaled(&ddq, x));
+                for i in 0..gravity.len() {
+                    constraints.push(Constraint {
+                        a: (inertia[i] - gravity[i]) / x,
+                        b: (coupling[i] - gravity[i]) / x,
+                        c: gravity[i],
+                        lower: -torque_limits[i],
+                        upper: torque_limits[i],
+                    });
+                }
+            }
+            Point {
+                constraints,
+                max_speed,
+            }
+        })
+        .collect();
+
+    let infeasible = |k: usize| MotionError::Infeasible {
+        fraction: k as f32 / n as f32,
+    };
+
+    // largest squared path speed at each point that can still brake to rest at the end
+    let mut brake = vec![0.0; n + 1];
+    for k in (0..n).rev() {
+        let can_brake = |x: f32| {
+            path[k]
+                .acceleration_range(x)
+                .is_some_and(|(min, _)| x + 2.0 * min <= brake[k + 1] + 1e-6)
+        };
+        if !can_brake(0.0) {
+            return Err(infeasible(k));
+        }
+        let (mut low, mut high) = (0.0, path[k].max_speed.min(MAX_PATH_SPEED));
+        if can_brake(high) {
+            low = high;
+        }
+        for _ in 0..BISECTION_STEPS {
+            let mid = (low + high) / 2.0;
+            if can_brake(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        brake[k] = low;
+    }
+
+    // accelerate as hard as possible until the braking curve is reached
+    let mut speeds = vec![0.0; n + 1];
+    let mut times = vec![0.0; n + 1];
+    let mut x = 0.0;
+    for k in 0..n {
+        let (_, max) = path[k].acceleration_range(x).ok_or_else(|| infeasible(k))?;
+        let next = (x + 2.0 * max).clamp(0.0, brake[k + 1]);
+        let (v, v_next) = (x.sqrt(), next.sqrt());
+        if v + v_next <= 0.0 {
+            return Err(infeasible(k));
+        }
+        times[k + 1] = times[k] + 2.0 / (v + v_next);
+        speeds[k + 1] = v_next;
+        x = next;
+    }
+
+    Ok(TimedPath {
+        points: points.to_vec(),
+        times,
+        speeds,
+    })
+}
+
+fn difference(before: &[f32], after: &[f32], ds: f32) -> Vec<f32> {
+    before
+        .iter()
+        .zip(after.iter())
+        .map(|(a, b)| (b - a) / ds)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::ArmModel;
+    use crate::robot::ArmDescription;
+
+    // fast joints that would need more torque than they have without the torque limits
+    fn model() -> ArmModel {
+        let mut description = ArmDescription::default();
+        for link in description.links.iter_mut() {
+            link.max_velocity = 180.0;
+            link.max_acceleration = 2000.0;
+            link.max_torque = 14.0;
+        }
+        ArmModel::new(description)
+    }
+
+    // straight joint path from the rest pose
+    fn path() -> Vec<Vec<f32>> {
+        let (from, to) = ([0.0, 0.0, -90.0], [-30.0, -60.0, -30.0]);
+        (0..=200)
+            .map(|k| {
+                let f = k as f32 / 200.0;
+                from.iter()
+                    .zip(to.iter())
+                    .map(|(a, b)| a + (b - a) * f)
+                    .collect()
+            })
+            .collect()
+    }
+
+    // joint velocities and torques at each path point, from the path speed and its change to
+    // the next point
+    fn profile(timed: &TimedPath, model: &ArmModel) -> Vec<(Vec<f32>, Vec<f32>)> {
+        let points = &timed.points;
+        let n = points.len() - 1;
+        (0..n)
+            .map(|k| {
+                let before = k.saturating_sub(1);
+                let dq = difference(&points[before], &points[k + 1], (k + 1 - before) as f32);
+                let ddq: Vec<f32> = (0..dq.len())
+                    .map(|i| {
+                        let k = k.clamp(1, n - 1);
+                        points[k - 1][i] - 2.0 * points[k][i] + points[k + 1][i]
+                    })
+                    .collect();
+                let (v, v_next) = (timed.speeds[k], timed.speeds[k + 1]);
+                let u = (v_next * v_next - v * v) / 2.0;
+                let velocities: Vec<f32> = dq.iter().map(|dq| dq * v).collect();
+                let accelerations: Vec<f32> = dq
+                    .iter()
+                    .zip(ddq.iter())
+                    .map(|(dq, ddq)| dq * u + ddq * v * v)
+                    .collect();
+                let torques = model.torques(&points[k], &velocities, &accelerations);
+                (velocities, torques)
+            })
+            .collect()
+    }
+
+    fn peak(values: impl Iterator<Item = f32>) -> f32 {
+        values.map(f32::abs).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn stays_within_limits() {
+        let model = model();
+        let limits = model.description.kinematic_limits();
+        let kinematic = time_optimal(&path(), &limits, None).unwrap();
+        let timed = time_optimal(&path(), &limits, Some(&model)).unwrap();
+
+        let torque = |timed: &TimedPath| {
+            peak(
+                profile(timed, &model)
+                    .into_iter()
+                    .flat_map(|(_, torques)| torques),
+            )
+        };
+        // only the torque limits slow this path down
+        assert!(torque(&kinematic) > 14.0);
+        assert!(torque(&timed) <= 14.0 * 1.001);
+        for (velocities, _) in profile(&timed, &model) {
+            for (velocity, limits) in velocities.iter().zip(limits.iter()) {
+                assert!(velocity.abs() <= limits.velocity * 1.001);
+            }
+        }
+    }
+
+    #[test]
+    fn no_faster_than_the_joints() {
+        let model = model();
+        let limits = model.description.kinematic_limits();
+        let path = path();
+        // every joint on its own accelerates, cruises and brakes as hard as it can
+        let bound = path[0]
+            .iter()
+            .zip(path[path.len() - 1].iter())
+            .zip(limits.iter())
+            .map(|((from, to), limits)| {
+                let (distance, v, a) = ((to - from).abs(), limits.velocity, limits.acceleration);
+                if distance >= v * v / a {
+                    distance / v + v / a
+                } else {
+                    2.0 * (distance / a).sqrt()
+                }
+            })
+            .fold(0.0, f32::max);
+
+        let kinematic = time_optimal(&path, &limits, None).unwrap();
+        let timed = time_optimal(&path, &limits, Some(&model)).unwrap();
+        assert!(kinematic.cycle_time() >= bound * (1.0 - 1e-4));
+        assert!(timed.cycle_time() >= kinematic.cycle_time());
+    }
+}
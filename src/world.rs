@@ -0,0 +1,1680 @@
+use crate::collision::{collide, collide_parts};
+use crate::dynamics::{ArmModel, Payload, GRAVITY};
+use crate::event::{Event, Obstacle};
+use crate::geometry::{polygon_centroid, Vec2};
+use crate::ik::{closest_solution, inverse_kinematics};
+use crate::kinematics::{point_velocity, tcp_pose, Frame, JointState};
+use crate::motion::{
+    plan_circular_move, plan_joint_move, plan_linear_move, Blend, Motion, MotionError, MotionMode,
+    Orientation,
+};
+use crate::physics::{create_backend, Body, PhysicsBackend, PhysicsMode};
+use crate::profile::{JogAxis, ProfileKind};
+use crate::robot::{
+    create, generate_random_object, Arm, ArmDescription, ArmPart, CellDescription, Color, Fixtures,
+    Part, Parts,
+};
+use crate::servo::Servo;
+use crate::timing::{time_optimal, Dynamics};
+
+// servo updates per simulation step
+const SERVO_SUBSTEPS: usize = 10;
+// penetration into an object at which the closing claws stop, in units
+const GRIP_TOLERANCE: f32 = 0.001;
+// bisection steps that find the claw angle where the claws touch an object
+const CONTACT_ITERATIONS: usize = 20;
+// times a closing claw pushes an object it touches alone by the grip tolerance towards the other
+const PUSH_ITERATIONS: usize = 100;
+// deeper penetration into the surroundings by which a push counts as running into them
+const PUSH_SLACK: f32 = 1.0e-6;
+// seconds between the poses of a motion that are checked for collisions before it starts
+const PATH_CHECK_INTERVAL: f32 = 0.01;
+// poses checked along a motion at most, longer motions are checked at wider intervals
+const MAX_PATH_CHECKS: usize = 1000;
+// penetration of the arm into the ground or an object that counts as running into it, the claws
+// touch the objects they close on by less
+const CONTACT_TOLERANCE: f32 = 2.0 * GRIP_TOLERANCE;
+
+pub struct State {
+    pub joints: JointState,
+    pub moved_object: String,
+    // radius around the waypoint in which a running program already moves on to the next
+    // one, 0 is a fine point where the arm stops
+    pub zone: f32,
+}
+
+// phase of a running move, the arm moves first and the claws follow
+enum Phase {
+    Move {
+        motion: Box<dyn Motion>,
+        elapsed: f32,
+    },
+    Claw,
+}
+
+// taught or commanded move that is being executed
+struct Execution {
+    phase: Phase,
+    target: JointState,
+    carried: Option<String>,
+    // blend radius around the target
+    zone: f32,
+}
+
+// object attached to the claws, stored in the wrist frame
+struct Grasp {
+    object: String,
+    // object in the wrist frame
+    local: Vec<Vec2>,
+    // velocity of the claws at the object center in the last step
+    velocity: Vec2,
+    // units per second the object slides out of the claws, negative into them
+    slip_velocity: f32,
+}
+
+pub struct World {
+    pub arm: Arm,
+    pub objects: Parts,
+    pub fixtures: Fixtures,
+    physics: Box<dyn PhysicsBackend>,
+    physics_mode: PhysicsMode,
+    ground: f32,
+    // simulated seconds since start
+    time: f32,
+
+    // grasp state
+    grasp: Option<Grasp>,
+    events: Vec<Event>,
+    moved_object: String,
+    // names of the spawned objects, the last one is removed first
+    spawned: Vec<String>,
+
+    // teach and jobs
+    jobs: Vec<State>,
+    state: Option<State>,
+    execution: Option<Execution>,
+    motion_mode: MotionMode,
+    profile: ProfileKind,
+    jog: Option<Vec<JogAxis>>,
+    // zone of newly taught positions
+    zone: f32,
+    // execute all taught positions one after another
+    running: bool,
+    // time at the start of the running program and duration of the last finished one
+    program_start: f32,
+    cycle_time: Option<f32>,
+    // retime moves to the fastest motion within the joint and torque limits
+    time_optimal: bool,
+    dynamics: Option<Box<dyn Dynamics>>,
+
+    // joint rates in degrees per second (squared) of the last step
+    velocities: Vec<f32>,
+    accelerations: Vec<f32>,
+    // joint torques the arm is driven by instead of the planned motion
+    torque_command: Option<Vec<f32>>,
+    // joints follow the planned motion through their servos instead of exactly
+    servo_control: bool,
+    servos: Vec<Servo>,
+    // setpoints of the servos
+    command: Vec<f32>,
+
+    // the arm ran into its surroundings and holds still until the stop is reset
+    protective_stop: bool,
+    // parts of the arm and what they touched after the last step
+    touching: Vec<(ArmPart, Obstacle)>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
+
+impl World {
+    pub fn new() -> World {
+        World::from_description(ArmDescription::default(), CellDescription::default())
+    }
+
+    pub fn from_description(description: ArmDescription, cell: CellDescription) -> World {
+        let (arm, objects, fixtures) = create(description, &cell);
+        let joints = arm.joints.angles.len();
+        World {
+            ground: cell.ground,
+            time: 0.0,
+            arm,
+            objects,
+            fixtures,
+            physics: create_backend(PhysicsMode::default()),
+            physics_mode: PhysicsMode::default(),
+            grasp: None,
+            events: Vec::new(),
+            moved_object: String::new(),
+            spawned: Vec::new(),
+            jobs: Vec::new(),
+            state: None,
+            execution: None,
+            motion_mode: MotionMode::default(),
+            profile: ProfileKind::default(),
+            jog: None,
+            zone: 0.0,
+            running: false,
+            program_start: 0.0,
+            cycle_time: None,
+            time_optimal: false,
+            dynamics: None,
+            velocities: vec![0.0; joints],
+            accelerations: vec![0.0; joints],
+            torque_command: None,
+            servo_control: false,
+            servos: vec![Servo::default(); joints],
+            command: Vec::new(),
+            protective_stop: false,
+            touching: Vec::new(),
+        }
+    }
+
+    // velocity and resting state of a free object
+    pub fn object_body(&self, name: &str) -> Option<Body> {
+        self.physics.body(name)
+    }
+
+    pub fn physics_mode(&self) -> PhysicsMode {
+        self.physics_mode
+    }
+
+    // switch the engine that moves the objects, they keep their place but come to a stop
+    pub fn set_physics_mode(&mut self, mode: PhysicsMode) {
+        if mode != self.physics_mode {
+            self.physics = create_backend(mode);
+            self.physics_mode = mode;
+        }
+    }
+
+    pub fn arm_parts(&self) -> impl Iterator<Item = &dyn Part> {
+        self.arm.parts()
+    }
+
+    pub fn joints(&self) -> &JointState {
+        &self.arm.joints
+    }
+
+    pub fn tcp(&self) -> Frame {
+        self.arm.tcp()
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn spawn_object(&mut self, bl: (f32, f32), color: Color) {
+        let object = Box::new(generate_random_object(bl, color));
+        // the first free name, configured objects may already be called like spawned ones
+        let name = (0..)
+            .map(|id| format!("obj{}", id))
+            .find(|name| !self.objects.contains_key(name))
+            .unwrap();
+        self.objects.insert(name.clone(), object);
+        self.spawned.push(name);
+    }
+
+    pub fn remove_last_object(&mut self) {
+        if let Some(name) = self.spawned.pop() {
+            self.objects.remove(&name);
+        }
+    }
+
+    pub fn teach(&mut self) {
+        self.jobs.insert(
+            0,
+            State {
+                joints: self.arm.joints.clone(),
+                moved_object: self.moved_object.clone(),
+                zone: self.zone,
+            },
+        );
+    }
+
+    pub fn zone(&self) -> f32 {
+        self.zone
+    }
+
+    // zone of the positions taught from now on
+    pub fn set_zone(&mut self, zone: f32) {
+        self.zone = zone;
+    }
+
+    // move to the next taught position and stop there
+    pub fn execute_next(&mut self) -> Result<(), MotionError> {
+        self.running = false;
+        self.execute_job()
+    }
+
+    // move through all remaining taught positions, blending them within their zones
+    pub fn run_program(&mut self) -> Result<(), MotionError> {
+        self.execute_job()?;
+        self.running = self.execution.is_some();
+        self.program_start = self.time;
+        Ok(())
+    }
+
+    // seconds the last program run took from its start until the arm came to rest
+    pub fn cycle_time(&self) -> Option<f32> {
+        self.cycle_time
+    }
+
+    pub fn is_time_optimal(&self) -> bool {
+        self.time_optimal
+    }
+
+    // retime all following moves to the fastest motion along their path, a path that cannot be
+    // followed within the limits keeps its velocity profile
+    pub fn set_time_optimal(&mut self, time_optimal: bool) {
+        self.time_optimal = time_optimal;
+    }
+
+    // inverse dynamics that limit joint torques of time-optimal moves instead of the arm model
+    pub fn set_dynamics(&mut self, dynamics: Option<Box<dyn Dynamics>>) {
+        self.dynamics = dynamics;
+    }
+
+    // rigid body model of the arm carrying the grasped object
+    pub fn model(&self) -> ArmModel {
+        let payload = self.grasp.as_ref().and_then(|grasp| {
+            let object = self.objects.get(grasp.object.as_str())?;
+            Some(Payload::from_polygon(&grasp.local, object.get_mass()))
+        });
+        ArmModel::new(self.arm.description.clone()).with_payload(payload)
+    }
+
+    pub fn joint_velocities(&self) -> &[f32] {
+        &self.velocities
+    }
+
+    pub fn joint_accelerations(&self) -> &[f32] {
+        &self.accelerations
+    }
+
+    // joint torques needed for the motion of the last step
+    pub fn joint_torques(&self) -> Vec<f32> {
+        self.model().inverse_dynamics(
+            &self.arm.joints.angles,
+            &self.velocities,
+            &self.accelerations,
+        )
+    }
+
+    // drive the joints by torques through the arm model instead of planned motions, `None`
+    // holds the arm where it is
+    pub fn command_torques(&mut self, torques: Option<Vec<f32>>) {
+        if self.protective_stop && torques.is_some() {
+            return;
+        }
+        self.execution = None;
+        self.jog = None;
+        self.running = false;
+        if torques.is_none() {
+            self.velocities.iter_mut().for_each(|v| *v = 0.0);
+        }
+        self.torque_command = torques;
+    }
+
+    pub fn is_servo_control(&self) -> bool {
+        self.servo_control
+    }
+
+    // track planned motions and jogging with the simulated joint servos, otherwise the joints
+    // are exactly where they are commanded
+    pub fn set_servo_control(&mut self, servo_control: bool) {
+        self.servo_control = servo_control;
+        self.command = self.arm.joints.angles.clone();
+        self.servos
+            .iter_mut()
+            .for_each(|servo| *servo = Servo::default());
+    }
+
+    // controller and motor state of every joint
+    pub fn servos(&self) -> &[Servo] {
+        &self.servos
+    }
+
+    // setpoint minus actual angle of every joint in degrees
+    pub fn tracking_errors(&self) -> Vec<f32> {
+        if !self.servo_control {
+            return vec![0.0; self.arm.joints.angles.len()];
+        }
+        self.command
+            .iter()
+            .zip(self.arm.joints.angles.iter())
+            .map(|(command, angle)| command - angle)
+            .collect()
+    }
+
+    pub fn set_servo_gains(&mut self, joint: usize, kp: f32, ki: f32, kd: f32) {
+        if let Some(link) = self.arm.description.links.get_mut(joint) {
+            link.servo.kp = kp;
+            link.servo.ki = ki;
+            link.servo.kd = kd;
+        }
+    }
+
+    fn retime(&self, motion: Box<dyn Motion>) -> Box<dyn Motion> {
+        if !self.time_optimal {
+            return motion;
+        }
+        let limits = self.arm.description.kinematic_limits();
+        let model = self.model();
+        let dynamics = self.dynamics.as_deref().unwrap_or(&model);
+        match time_optimal(&motion.path(), &limits, Some(dynamics)) {
+            Ok(timed) => Box::new(timed),
+            Err(_) => motion,
+        }
+    }
+
+    // start the move to the next taught position, a step that cannot start ends the program and
+    // is dropped
+    fn execute_job(&mut self) -> Result<(), MotionError> {
+        if self.protective_stop {
+            self.running = false;
+            return Err(MotionError::ProtectiveStop);
+        }
+        self.state = self.jobs.pop();
+        let Some(state) = self.state.as_ref() else {
+            return Ok(());
+        };
+        let carried = (!state.moved_object.is_empty()).then(|| state.moved_object.clone());
+        let zone = self.blend_zone(&self.arm.joints.angles, state);
+        if let Err(error) = self.start(state.joints.clone(), carried) {
+            self.state = None;
+            self.running = false;
+            return Err(error);
+        }
+        if let Some(execution) = self.execution.as_mut() {
+            execution.zone = zone;
+        }
+        Ok(())
+    }
+
+    // zone of `state` when approached from `from`, at most half the distance so that
+    // close waypoints are not skipped
+    fn blend_zone(&self, from: &[f32], state: &State) -> f32 {
+        let description = &self.arm.description;
+        let distance = (tcp_pose(description, &state.joints.angles).origin
+            - tcp_pose(description, from).origin)
+            .length();
+        state.zone.min(distance / 2.0)
+    }
+
+    pub fn motion_mode(&self) -> MotionMode {
+        self.motion_mode
+    }
+
+    pub fn set_motion_mode(&mut self, mode: MotionMode) {
+        self.motion_mode = mode;
+    }
+
+    pub fn profile(&self) -> ProfileKind {
+        self.profile
+    }
+
+    // velocity profile of jogging and joint moves, running moves keep their profile
+    pub fn set_profile(&mut self, profile: ProfileKind) {
+        self.profile = profile;
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.execution.is_some() || self.jog.is_some()
+    }
+
+    // motion of the running move, sample it to analyse the planned profile
+    pub fn planned_motion(&self) -> Option<&dyn Motion> {
+        match self.execution.as_ref().map(|execution| &execution.phase) {
+            Some(Phase::Move { motion, .. }) => Some(motion.as_ref()),
+            _ => None,
+        }
+    }
+
+    // start moving towards `target`, replacing any running move
+    fn start(&mut self, target: JointState, carried: Option<String>) -> Result<(), MotionError> {
+        let motion = self.plan_joint_move(&self.arm.joints.angles, &target.angles);
+        self.start_motion(motion, target, carried)
+    }
+
+    fn start_motion(
+        &mut self,
+        motion: Box<dyn Motion>,
+        target: JointState,
+        carried: Option<String>,
+    ) -> Result<(), MotionError> {
+        if self.protective_stop {
+            return Err(MotionError::ProtectiveStop);
+        }
+        self.jog = None;
+        self.torque_command = None;
+        // motions start from the actual angles, the setpoints must not jump
+        self.command = self.arm.joints.angles.clone();
+        let motion = self.retime(motion);
+        if !motion.duration().is_finite() {
+            self.execution = None;
+            self.running = false;
+            return Err(MotionError::Infeasible { fraction: 0.0 });
+        }
+        if let Some((first, second)) = self.path_collision(motion.as_ref(), 0.0, target.claw) {
+            self.execution = None;
+            self.running = false;
+            self.events.push(Event::SelfCollision {
+                first,
+                second,
+                time: self.time,
+            });
+            return Err(MotionError::SelfCollision { first, second });
+        }
+        self.execution = Some(Execution {
+            phase: Phase::Move {
+                motion,
+                elapsed: 0.0,
+            },
+            target,
+            carried,
+            zone: 0.0,
+        });
+        Ok(())
+    }
+
+    // parts of the arm that would run into each other along `motion` after `from` seconds and
+    // with the claws at `claw` at its end, an arm that already collides may move out again
+    fn path_collision(
+        &mut self,
+        motion: &dyn Motion,
+        from: f32,
+        claw: f32,
+    ) -> Option<(ArmPart, ArmPart)> {
+        if self.arm.self_collision().is_some() {
+            return None;
+        }
+        let joints = self.arm.joints.clone();
+        let span = motion.duration() - from;
+        let samples = ((span / PATH_CHECK_INTERVAL).ceil() as usize).clamp(1, MAX_PATH_CHECKS);
+        let mut collision = None;
+        for k in 1..=samples + 1 {
+            let time = from + span * k.min(samples) as f32 / samples as f32;
+            self.arm.joints.angles = motion.sample(time);
+            if k > samples {
+                self.arm.joints.claw = claw;
+            }
+            self.arm.update();
+            collision = self.arm.self_collision();
+            if collision.is_some() {
+                break;
+            }
+        }
+        self.arm.joints = joints;
+        self.arm.update();
+        collision
+    }
+
+    // undo the last change of the joints if it made the arm run into itself, the arm stops there
+    fn stop_self_collision(&mut self, angles: Vec<f32>, claw: f32) {
+        let Some((first, second)) = self.arm.self_collision() else {
+            return;
+        };
+        let joints = std::mem::replace(
+            &mut self.arm.joints,
+            JointState {
+                angles: angles.clone(),
+                claw,
+            },
+        );
+        self.arm.update();
+        if self.arm.self_collision().is_some() {
+            // it collided before, moving on may free it
+            self.arm.joints = joints;
+            self.update();
+            return;
+        }
+        self.update();
+        self.halt(angles);
+        self.events.push(Event::SelfCollision {
+            first,
+            second,
+            time: self.time,
+        });
+    }
+
+    // undo the last change of the joints if it made the arm run into something it did not touch
+    // before, the arm stops there until the protective stop is reset
+    fn stop_environment_collision(&mut self, angles: Vec<f32>, claw: f32) {
+        let collisions = self.environment_collisions();
+        let Some((part, obstacle, position)) = collisions
+            .iter()
+            .find(|(part, obstacle, _)| !self.touching.contains(&(*part, obstacle.clone())))
+            .cloned()
+        else {
+            self.touching = touched(collisions);
+            return;
+        };
+        self.arm.joints = JointState {
+            angles: angles.clone(),
+            claw,
+        };
+        self.update();
+        self.touching = touched(self.environment_collisions());
+        self.halt(angles);
+        self.protective_stop = true;
+        self.events.push(Event::ProtectiveStop {
+            part,
+            obstacle,
+            position,
+            time: self.time,
+        });
+    }
+
+    // parts of the arm that cut into the ground, a fixture or a free object and the deepest point
+    // of each
+    fn environment_collisions(&self) -> Vec<(ArmPart, Obstacle, Vec2)> {
+        let mut collisions = Vec::new();
+        for (part, pieces) in self.arm.collision_shapes() {
+            // the first link is mounted on the ground
+            let lowest = pieces
+                .iter()
+                .flatten()
+                .min_by(|a, b| a.y.total_cmp(&b.y))
+                .filter(|v| v.y < self.ground - CONTACT_TOLERANCE);
+            if let Some(lowest) = lowest.filter(|_| part != ArmPart::Link(0)) {
+                collisions.push((part, Obstacle::Ground, *lowest));
+            }
+            let fixtures = self
+                .fixtures
+                .iter()
+                .map(|(name, fixture)| (Obstacle::Fixture(name.clone()), fixture as &dyn Part));
+            let objects = self
+                .objects
+                .iter()
+                .filter(|(name, _)| !self.is_held(name))
+                .map(|(name, object)| (Obstacle::Object(name.clone()), object.as_ref()));
+            for (obstacle, other) in fixtures.chain(objects) {
+                let deepest = pieces
+                    .iter()
+                    .filter_map(|piece| collide(piece, other.get_vertices_ref()))
+                    .flat_map(|contact| contact.points)
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .filter(|(_, depth)| *depth > CONTACT_TOLERANCE);
+                if let Some((point, _)) = deepest {
+                    collisions.push((part, obstacle, point));
+                }
+            }
+        }
+        collisions
+    }
+
+    // objects the arm moves instead of the physics
+    fn is_held(&self, name: &str) -> bool {
+        name == self.moved_object
+            || self
+                .state
+                .as_ref()
+                .is_some_and(|state| state.moved_object == name)
+            || self.grasped_object() == Some(name)
+    }
+
+    // stop all motion with the setpoints at `angles`
+    fn halt(&mut self, angles: Vec<f32>) {
+        self.command = angles;
+        self.velocities.iter_mut().for_each(|v| *v = 0.0);
+        self.jog = None;
+        self.execution = None;
+        self.running = false;
+        self.torque_command = None;
+    }
+
+    pub fn is_protective_stop(&self) -> bool {
+        self.protective_stop
+    }
+
+    // let the arm move again after a protective stop, what it touches now does not stop it again
+    pub fn reset_protective_stop(&mut self) {
+        self.protective_stop = false;
+        self.touching = touched(self.environment_collisions());
+    }
+
+    fn plan_joint_move(&self, start: &[f32], target: &[f32]) -> Box<dyn Motion> {
+        plan_joint_move(
+            self.motion_mode,
+            self.profile,
+            start,
+            target,
+            &self.arm.description.kinematic_limits(),
+        )
+    }
+
+    // move the tool center point to `target` using the closest inverse kinematics solution
+    pub fn move_to(&mut self, target: Frame) -> Result<(), MotionError> {
+        let solutions = inverse_kinematics(&self.arm.description, target).map_err(|source| {
+            MotionError::Unreachable {
+                fraction: 1.0,
+                source,
+            }
+        })?;
+        let solution = closest_solution(&solutions, &self.arm.joints.angles).unwrap();
+        let motion = self.plan_joint_move(&self.arm.joints.angles, &solution.angles);
+        self.start_carrying(motion)
+    }
+
+    // move the tool center point along a straight line to `target` with `speed` units per second
+    pub fn move_linear(&mut self, target: Frame, speed: f32) -> Result<(), MotionError> {
+        let motion = plan_linear_move(
+            &self.arm.description,
+            &self.arm.joints.angles,
+            target,
+            speed,
+        )?;
+        self.start_carrying(Box::new(motion))
+    }
+
+    // move the tool center point along the arc through `via` to `end` with `speed` units per second
+    pub fn move_circular(
+        &mut self,
+        via: Vec2,
+        end: Vec2,
+        orientation: Orientation,
+        speed: f32,
+    ) -> Result<(), MotionError> {
+        let motion = plan_circular_move(
+            &self.arm.description,
+            &self.arm.joints.angles,
+            via,
+            end,
+            orientation,
+            speed,
+        )?;
+        self.start_carrying(Box::new(motion))
+    }
+
+    // start a tool motion that keeps the claw and the grasped object
+    fn start_carrying(&mut self, motion: Box<dyn Motion>) -> Result<(), MotionError> {
+        let target = JointState {
+            angles: motion.sample(motion.duration()),
+            claw: self.arm.joints.claw,
+        };
+        let carried = self.grasp.as_ref().map(|grasp| grasp.object.clone());
+        self.running = false;
+        self.start_motion(motion, target, carried)
+    }
+
+    pub fn set_jog_increment(&mut self, joint: usize, increment: f32) {
+        if let Some(link) = self.arm.description.links.get_mut(joint) {
+            link.jog_increment = increment;
+        }
+    }
+
+    pub fn release_manually(&mut self) {
+        self.grasp = None;
+    }
+
+    pub fn return_to_base(&mut self) -> Result<(), MotionError> {
+        self.running = false;
+        self.start(self.arm.description.rest(), None)
+    }
+
+    // let go of the grasped object, it keeps the velocity the claws had
+    fn release(&mut self) {
+        let Some(grasp) = self.grasp.take() else {
+            return;
+        };
+        if let Some(object) = self.objects.get(grasp.object.as_str()) {
+            let center = polygon_centroid(object.get_vertices_ref());
+            let velocity =
+                self.claw_velocity(center) + self.arm.wrist().direction() * grasp.slip_velocity;
+            self.physics
+                .set_velocity(&grasp.object, velocity, self.velocities.iter().sum());
+        }
+    }
+
+    fn attach(&mut self, name: &str) {
+        if let Some(object) = self.objects.get(name) {
+            let wrist = self.arm.wrist();
+            self.grasp = Some(Grasp {
+                object: name.to_string(),
+                local: object
+                    .get_vertices_ref()
+                    .iter()
+                    .map(|v| wrist.inverse_transform(*v))
+                    .collect(),
+                velocity: self.claw_velocity(polygon_centroid(object.get_vertices_ref())),
+                slip_velocity: 0.0,
+            });
+        }
+    }
+
+    // velocity of `point` moving along with the claws
+    fn claw_velocity(&self, point: Vec2) -> Vec2 {
+        point_velocity(
+            &self.arm.description,
+            &self.arm.joints.angles,
+            &self.velocities,
+            point,
+        )
+    }
+
+    // the grasped object slides along the claws when their friction cannot hold it against its
+    // weight and the acceleration of the arm, it falls out once it no longer touches both claws
+    fn slip(&mut self, dt: f32) {
+        let Some(grasp) = self.grasp.as_ref() else {
+            return;
+        };
+        let Some(object) = self.objects.get(grasp.object.as_str()) else {
+            return;
+        };
+        let (mass, center) = (
+            object.get_mass(),
+            polygon_centroid(object.get_vertices_ref()),
+        );
+        let velocity = self.claw_velocity(center);
+        let acceleration = (velocity - grasp.velocity) * (1.0 / dt);
+        let axis = self.arm.wrist().direction();
+        // force that pulls the object out of the claws, as seen from the moving claws
+        let pull = mass * (Vec2::new(0.0, -GRAVITY) - acceleration).dot(axis);
+        let claw = &self.arm.description.claw;
+        let friction = 2.0 * claw.friction * claw.grip_force;
+
+        let grasp = self.grasp.as_mut().unwrap();
+        grasp.velocity = velocity;
+        if grasp.slip_velocity == 0.0 && pull.abs() <= friction {
+            return;
+        }
+        // kinetic friction acts against the sliding, it stops the object again
+        let direction = if grasp.slip_velocity != 0.0 {
+            grasp.slip_velocity.signum()
+        } else {
+            pull.signum()
+        };
+        let mut slip_velocity = grasp.slip_velocity + (pull - friction * direction) / mass * dt;
+        if slip_velocity * direction < 0.0 {
+            slip_velocity = 0.0;
+        }
+        // the object cannot slide into the wrist
+        let inner = grasp
+            .local
+            .iter()
+            .map(|v| v.x)
+            .fold(f32::INFINITY, f32::min);
+        let mut offset = slip_velocity * dt;
+        if offset < 0.0 && inner + offset < 0.0 {
+            offset = (-inner).min(0.0);
+            slip_velocity = 0.0;
+        }
+        grasp.local.iter_mut().for_each(|v| v.x += offset);
+        grasp.slip_velocity = slip_velocity;
+        let name = grasp.object.clone();
+        self.update();
+
+        if !self.grips(Some(&name)) {
+            self.release();
+            if self.moved_object == name {
+                self.moved_object = String::new();
+            }
+            if let Some(state) = self
+                .state
+                .as_mut()
+                .filter(|state| state.moved_object == name)
+            {
+                state.moved_object = String::new();
+            }
+            // a running program does not go on without the object
+            if self.running {
+                self.running = false;
+                self.execution = None;
+            }
+            let position = polygon_centroid(self.objects[&name].get_vertices_ref());
+            self.events.push(Event::GraspLost {
+                object: name,
+                time: self.time,
+                position,
+            });
+        }
+    }
+
+    // recompute the arm and the grasped object after the joints changed
+    fn update(&mut self) {
+        self.arm.update();
+        if let Some(grasp) = self.grasp.as_ref() {
+            let wrist = self.arm.wrist();
+            if let Some(object) = self.objects.get_mut(grasp.object.as_str()) {
+                *object.get_vertices() = grasp.local.iter().map(|v| wrist.transform(*v)).collect();
+            }
+        }
+    }
+
+    fn grips(&self, object: Option<&str>) -> bool {
+        object
+            .and_then(|name| self.objects.get(name))
+            .is_some_and(|object| self.claws_touch(object.as_ref()))
+    }
+
+    // both claws are in contact with `object`
+    fn claws_touch(&self, object: &dyn Part) -> bool {
+        self.arm
+            .claws
+            .iter()
+            .all(|claw| collide_parts(claw, object).is_some())
+    }
+
+    fn claws_collide(&self) -> Option<String> {
+        self.objects
+            .iter()
+            .find(|(_, object)| self.claws_touch(object.as_ref()))
+            .map(|(name, _)| name.clone())
+    }
+
+    // move joint `i` by its jog increment, the joint ramps up and down with the current profile
+    // and repeated jogs extend the target, a running move is stopped
+    pub fn jog(&mut self, i: usize, ccw: bool) {
+        let Some(link) = self.arm.description.links.get(i) else {
+            return;
+        };
+        if self.protective_stop {
+            return;
+        }
+        let ((min, max), increment) = (link.limits, link.jog_increment);
+        let limits = link.kinematic_limits();
+        self.execution = None;
+        self.running = false;
+        self.torque_command = None;
+        if self.jog.is_none() {
+            self.command = self.arm.joints.angles.clone();
+        }
+        let angles = &self.arm.joints.angles;
+        let axis = &mut self
+            .jog
+            .get_or_insert_with(|| angles.iter().map(|a| JogAxis::new(*a)).collect())[i];
+        let angle = axis.target();
+        if ccw && angle < max {
+            axis.set_target((angle + increment).min(max), limits, self.profile);
+        } else if !ccw && angle > min {
+            axis.set_target((angle - increment).max(min), limits, self.profile);
+        }
+    }
+
+    pub fn open_claws(&mut self) {
+        let (_, max) = self.arm.description.claw_limits();
+        let increment = self.arm.description.claw.jog_increment;
+        if self.arm.joints.claw < max && !self.protective_stop {
+            let claw = self.arm.joints.claw;
+            self.arm.joints.claw = (claw + increment).min(max);
+            self.update();
+            self.stop_self_collision(self.arm.joints.angles.clone(), claw);
+            self.stop_environment_collision(self.arm.joints.angles.clone(), claw);
+
+            if self.claws_collide().is_none() {
+                self.release();
+                self.moved_object = String::new();
+            }
+        }
+    }
+
+    pub fn close_claws(&mut self) {
+        let (min, _) = self.arm.description.claw_limits();
+        let increment = self.arm.description.claw.jog_increment;
+        if self.arm.joints.claw > min && !self.protective_stop {
+            let claw = self.arm.joints.claw;
+            self.close_towards((claw - increment).max(min));
+            self.stop_self_collision(self.arm.joints.angles.clone(), claw);
+            self.stop_environment_collision(self.arm.joints.angles.clone(), claw);
+
+            if let Some(name) = self.claws_collide() {
+                if self.grasp.is_none() && self.can_hold(&name) {
+                    self.attach(name.as_str());
+                    self.moved_object = name;
+                }
+            }
+        }
+    }
+
+    // distance between the claw tips, the width of the object the claws close on
+    pub fn grip_width(&self) -> f32 {
+        let [first, second] = &self.arm.claws;
+        (first.vertices[2] - second.vertices[2]).length()
+    }
+
+    // events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn grasped_object(&self) -> Option<&str> {
+        self.grasp.as_ref().map(|grasp| grasp.object.as_str())
+    }
+
+    pub fn set_grip_force(&mut self, force: f32) {
+        self.arm.description.claw.grip_force = force.max(0.0);
+    }
+
+    // close the claws towards `target`, they stop where they touch an object and false is returned,
+    // an object that only one claw touches is pushed along until both claws touch it
+    fn close_towards(&mut self, target: f32) -> bool {
+        // objects the claws already cut into do not stop them, the ones they touch do
+        let blocking: Vec<String> = self
+            .objects
+            .keys()
+            .filter(|name| self.claw_depth(name) < 2.0 * GRIP_TOLERANCE)
+            .cloned()
+            .collect();
+        let blocked = |world: &World| {
+            blocking
+                .iter()
+                .any(|name| world.claw_depth(name) > GRIP_TOLERANCE)
+        };
+
+        for _ in 0..PUSH_ITERATIONS {
+            let start = self.arm.joints.claw;
+            self.arm.joints.claw = target;
+            self.update();
+            if !blocked(self) {
+                return true;
+            }
+            let (mut open, mut closed) = (start, target);
+            for _ in 0..CONTACT_ITERATIONS {
+                self.arm.joints.claw = (open + closed) / 2.0;
+                self.update();
+                if blocked(self) {
+                    closed = self.arm.joints.claw;
+                } else {
+                    open = self.arm.joints.claw;
+                }
+            }
+            self.arm.joints.claw = open;
+            self.update();
+
+            let pushed = blocking.iter().find_map(|name| {
+                let object = self.objects.get(name)?;
+                let mut contacts = self
+                    .arm
+                    .claws
+                    .iter()
+                    .filter_map(|claw| collide_parts(claw, object.as_ref()));
+                let contact = contacts.next()?;
+                contacts
+                    .next()
+                    .is_none()
+                    .then(|| (name.clone(), contact.normal))
+            });
+            let Some((name, normal)) = pushed else {
+                return false;
+            };
+            // across the claws, away from the one that touches
+            let across = self.arm.wrist().direction().perp();
+            let offset = across * (GRIP_TOLERANCE * normal.dot(across).signum());
+            let before = self.obstruction(&name);
+            self.translate_object(&name, offset);
+            // the object is not pushed into the ground, a fixture or another object
+            if self.obstruction(&name) > before + PUSH_SLACK {
+                self.translate_object(&name, -offset);
+                return false;
+            }
+        }
+        false
+    }
+
+    fn translate_object(&mut self, name: &str, offset: Vec2) {
+        if let Some(object) = self.objects.get_mut(name) {
+            object.get_vertices().iter_mut().for_each(|v| *v += offset);
+        }
+    }
+
+    // deepest penetration of an object into the ground, the fixtures and the other objects
+    fn obstruction(&self, name: &str) -> f32 {
+        let Some(object) = self.objects.get(name) else {
+            return 0.0;
+        };
+        let ground = object
+            .get_vertices_ref()
+            .iter()
+            .map(|v| self.ground - v.y)
+            .fold(0.0, f32::max);
+        let others = self
+            .objects
+            .iter()
+            .filter(|(other, _)| other.as_str() != name)
+            .map(|(_, other)| other.as_ref());
+        self.fixtures
+            .values()
+            .map(|fixture| fixture as &dyn Part)
+            .chain(others)
+            .filter_map(|other| collide_parts(object.as_ref(), other))
+            .map(|contact| contact.depth)
+            .fold(ground, f32::max)
+    }
+
+    // deepest penetration of the claws into the object
+    fn claw_depth(&self, name: &str) -> f32 {
+        let Some(object) = self.objects.get(name) else {
+            return 0.0;
+        };
+        self.arm
+            .claws
+            .iter()
+            .filter_map(|claw| collide_parts(claw, object.as_ref()))
+            .map(|contact| contact.depth)
+            .fold(0.0, f32::max)
+    }
+
+    // the friction of both claws carries the weight of the object
+    fn can_hold(&self, name: &str) -> bool {
+        let claw = &self.arm.description.claw;
+        self.objects.get(name).is_some_and(|object| {
+            2.0 * claw.friction * claw.grip_force >= object.get_mass() * GRAVITY
+        })
+    }
+
+    // advance the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        self.time += dt;
+        self.simulate_objects(dt);
+        if self.protective_stop {
+            // the brakes hold the arm until the stop is reset
+            self.accelerations.iter_mut().for_each(|a| *a = 0.0);
+            self.slip(dt);
+            return;
+        }
+        let (angles, velocities) = (self.arm.joints.angles.clone(), self.velocities.clone());
+        let claw = self.arm.joints.claw;
+        if self.servo_control && self.torque_command.is_none() {
+            // moves and jogging advance the setpoints, they stay put while the arm is idle
+            self.arm.joints.angles = self.command.clone();
+        }
+
+        if let Some(mut execution) = self.execution.take() {
+            if self.execute(&mut execution, dt) {
+                self.execution = Some(execution);
+            } else if self.running {
+                match self.execute_job() {
+                    Ok(()) => {
+                        self.running = self.execution.is_some();
+                        if !self.running {
+                            self.cycle_time = Some(self.time - self.program_start);
+                        }
+                    }
+                    // self-collisions are already reported by `start_motion`
+                    Err(MotionError::SelfCollision { .. }) => {}
+                    Err(error) => self.events.push(Event::ProgramAborted {
+                        error,
+                        time: self.time,
+                    }),
+                }
+            }
+        }
+        if let Some(mut jog) = self.jog.take() {
+            if self.follow(&mut jog, dt) {
+                self.jog = Some(jog);
+            }
+        }
+
+        if let Some(torques) = self.torque_command.clone() {
+            self.simulate_torques(&torques, dt);
+            self.command = self.arm.joints.angles.clone();
+        } else if self.servo_control {
+            // the joints follow the setpoints through their servos
+            let command = std::mem::replace(&mut self.arm.joints.angles, angles.clone());
+            self.track(command, dt);
+        } else {
+            for (i, angle) in self.arm.joints.angles.iter().enumerate() {
+                self.velocities[i] = (angle - angles[i]) / dt;
+            }
+        }
+        self.stop_self_collision(angles.clone(), claw);
+        self.stop_environment_collision(angles, claw);
+        for (i, velocity) in self.velocities.iter().enumerate() {
+            self.accelerations[i] = (velocity - velocities[i]) / dt;
+        }
+        self.slip(dt);
+    }
+
+    // integrate the arm model under joint torques
+    fn simulate_torques(&mut self, torques: &[f32], dt: f32) {
+        self.integrate(&self.model(), torques, dt);
+        self.update();
+    }
+
+    // drive the joints towards the commanded angles through their servos, the motion of the
+    // last step is interpolated over several substeps because the motors are stiff
+    fn track(&mut self, command: Vec<f32>, dt: f32) {
+        let model = self.model();
+        let dt = dt / SERVO_SUBSTEPS as f32;
+        let previous = std::mem::replace(&mut self.command, command);
+        let rates: Vec<f32> = previous
+            .iter()
+            .zip(self.command.iter())
+            .map(|(a, b)| (b - a) / (dt * SERVO_SUBSTEPS as f32))
+            .collect();
+        for k in 1..=SERVO_SUBSTEPS {
+            let mut torques = Vec::with_capacity(self.servos.len());
+            for (i, servo) in self.servos.iter_mut().enumerate() {
+                let setpoint = previous[i] + rates[i] * dt * k as f32;
+                torques.push(servo.torque(
+                    &self.arm.description.links[i].servo,
+                    setpoint - self.arm.joints.angles[i],
+                    rates[i] - self.velocities[i],
+                    self.velocities[i],
+                    dt,
+                ));
+            }
+            self.integrate(&model, &torques, dt);
+        }
+        self.update();
+    }
+
+    // advance the joints under `torques` by the arm model, the joints stop at their limits
+    fn integrate(&mut self, model: &ArmModel, torques: &[f32], dt: f32) {
+        let accelerations =
+            model.forward_dynamics(&self.arm.joints.angles, &self.velocities, torques);
+        let limits = self.arm.description.limits();
+        for (i, angle) in self.arm.joints.angles.iter_mut().enumerate() {
+            self.velocities[i] += accelerations[i] * dt;
+            *angle += self.velocities[i] * dt;
+            let (min, max) = limits[i];
+            if *angle < min || *angle > max {
+                *angle = angle.clamp(min, max);
+                self.velocities[i] = 0.0;
+            }
+        }
+    }
+
+    // advance jogging joints, returns false once all of them reached their target
+    fn follow(&mut self, jog: &mut [JogAxis], dt: f32) -> bool {
+        let limits = self.arm.description.kinematic_limits();
+        for (i, axis) in jog.iter_mut().enumerate() {
+            self.arm.joints.angles[i] = axis.advance(dt, limits[i], self.profile);
+        }
+        self.update();
+        jog.iter().any(|axis| !axis.is_done())
+    }
+
+    // advance a running move, returns false once it is finished
+    fn execute(&mut self, execution: &mut Execution, dt: f32) -> bool {
+        let carried = execution.carried.as_deref();
+        let target = execution.target.claw;
+        match &mut execution.phase {
+            Phase::Move { motion, elapsed } => {
+                *elapsed += dt;
+                self.arm.joints.angles = motion.sample(*elapsed);
+                if *elapsed >= motion.duration() {
+                    execution.phase = Phase::Claw;
+                }
+                self.update();
+                if self.in_zone(execution) {
+                    self.blend(execution);
+                }
+            }
+            Phase::Claw => {
+                let step = self.arm.description.claw.max_velocity * dt;
+                let claw = &mut self.arm.joints.claw;
+                if *claw < target {
+                    *claw = (*claw + step).min(target);
+                    self.update();
+                    if !self.grips(carried) {
+                        let released = self.grasp.as_ref().map(|grasp| grasp.object.clone());
+                        self.release();
+                        // the released object is free to fall
+                        if let Some(state) = self.state.as_mut() {
+                            state.moved_object = String::new();
+                        }
+                        if released.is_some_and(|name| name == self.moved_object) {
+                            self.moved_object = String::new();
+                        }
+                    }
+                } else if *claw > target {
+                    let next = (*claw - step).max(target);
+                    let closing = self.close_towards(next);
+                    if self.grasp.is_none()
+                        && self.grips(carried)
+                        && self.can_hold(carried.unwrap())
+                    {
+                        self.attach(carried.unwrap());
+                    }
+                    // the claws stopped on an object
+                    return closing;
+                } else {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // the arm entered the zone of a waypoint with no claw action, the next one can be blended
+    fn in_zone(&self, execution: &Execution) -> bool {
+        let target = tcp_pose(&self.arm.description, &execution.target.angles);
+        self.running
+            && matches!(execution.phase, Phase::Move { .. })
+            && execution.target.claw == self.arm.joints.claw
+            && (self.tcp().origin - target.origin).length() <= execution.zone
+    }
+
+    // continue with the next taught position while the current move is still running
+    fn blend(&mut self, execution: &mut Execution) {
+        let Some(next) = self.jobs.pop() else {
+            return;
+        };
+        let second =
+            self.retime(self.plan_joint_move(&execution.target.angles, &next.joints.angles));
+        let Phase::Move { motion, elapsed } = std::mem::replace(&mut execution.phase, Phase::Claw)
+        else {
+            return;
+        };
+        let blended = Blend::new(motion, second, elapsed);
+        if self
+            .path_collision(&blended, elapsed, next.joints.claw)
+            .is_some()
+        {
+            // the corner would swing the arm into itself, it stops at the step instead
+            execution.phase = Phase::Move {
+                motion: blended.into_first(),
+                elapsed,
+            };
+            execution.zone = 0.0;
+            self.jobs.push(next);
+            return;
+        }
+        execution.phase = Phase::Move {
+            motion: Box::new(blended),
+            elapsed,
+        };
+        execution.zone = self.blend_zone(&execution.target.angles, &next);
+        execution.target = next.joints.clone();
+        execution.carried = (!next.moved_object.is_empty()).then(|| next.moved_object.clone());
+        self.state = Some(next);
+    }
+
+    // objects fall and stack, the ones carried by the arm are moved with the wrist instead
+    fn simulate_objects(&mut self, dt: f32) {
+        let state_object = self
+            .state
+            .as_ref()
+            .map(|state| state.moved_object.as_str())
+            .unwrap_or_default();
+        let grasped = self.grasp.as_ref().map(|grasp| grasp.object.as_str());
+        let held: Vec<&str> = [self.moved_object.as_str(), state_object]
+            .into_iter()
+            .chain(grasped)
+            .filter(|name| !name.is_empty())
+            .collect();
+        let arm: Vec<&dyn Part> = self.arm.parts().collect();
+        let fixtures: Vec<&dyn Part> = self.fixtures.values().map(|f| f as &dyn Part).collect();
+        self.physics
+            .step(&mut self.objects, &arm, &fixtures, self.ground, &held, dt);
+    }
+}
+
+// the parts and obstacles of `collisions`
+fn touched(collisions: Vec<(ArmPart, Obstacle, Vec2)>) -> Vec<(ArmPart, Obstacle)> {
+    collisions
+        .into_iter()
+        .map(|(part, obstacle, _)| (part, obstacle))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::{generate_fixture, GROUND};
+
+    const DT: f32 = 1.0 / 200.0;
+
+    fn run(world: &mut World, seconds: f32) {
+        for _ in 0..(seconds / DT) as usize {
+            world.step(DT);
+        }
+    }
+
+    // the open claws pointing down around an object whose center is `offset` off their axis
+    fn around_object(offset: f32) -> World {
+        let mut world = World::new();
+        world.set_physics_mode(PhysicsMode::Simple);
+        world.objects.clear();
+        world.spawn_object((-0.075 + offset, GROUND), [0.0; 3]);
+        for _ in 0..10 {
+            world.open_claws();
+        }
+        let above = Frame {
+            origin: Vec2::new(-0.05, -0.26),
+            angle: -90.0,
+        };
+        world.move_to(above).unwrap();
+        run(&mut world, 3.0);
+        world
+            .move_to(Frame {
+                origin: Vec2::new(-0.05, -0.35),
+                ..above
+            })
+            .unwrap();
+        run(&mut world, 2.0);
+        world
+    }
+
+    // keeps the arm at `angles` for `duration` seconds
+    struct Still {
+        angles: Vec<f32>,
+        duration: f32,
+    }
+
+    impl Motion for Still {
+        fn duration(&self) -> f32 {
+            self.duration
+        }
+
+        fn sample(&self, _: f32) -> Vec<f32> {
+            self.angles.clone()
+        }
+    }
+
+    #[test]
+    fn rejects_endless_motions() {
+        let mut world = World::new();
+        let joints = world.arm.joints.clone();
+        for duration in [f32::INFINITY, f32::NAN] {
+            let still = Still {
+                angles: joints.angles.clone(),
+                duration,
+            };
+            assert_eq!(
+                world.start_motion(Box::new(still), joints.clone(), None),
+                Err(MotionError::Infeasible { fraction: 0.0 })
+            );
+            assert!(!world.is_moving());
+        }
+        // long motions are checked at wider intervals
+        let still = Still {
+            angles: joints.angles.clone(),
+            duration: 1.0e9,
+        };
+        assert_eq!(
+            world.start_motion(Box::new(still), joints.clone(), None),
+            Ok(())
+        );
+        assert!(world.is_moving());
+    }
+
+    #[test]
+    fn blends_only_corners_clear_of_the_arm() {
+        // a fourth link lets the corner between two clear moves swing the arm into itself
+        let mut description = ArmDescription::default();
+        let mut link = description.links[2].clone();
+        link.limits = (-162.0, 162.0);
+        link.rest = 0.0;
+        description.links.push(link);
+        let cell = CellDescription {
+            ground: -10.0,
+            objects: Vec::new(),
+            fixtures: Vec::new(),
+        };
+        let mut world = World::from_description(description, cell);
+        world.set_zone(1.0);
+        for angles in [[-40.0, -40.0, -30.0, -50.0], [-20.0, 0.0, -160.0, 30.0]] {
+            world.arm.joints.angles = angles.to_vec();
+            world.arm.update();
+            world.teach();
+        }
+        world.arm.joints.angles = vec![-30.0, -90.0, -110.0, 160.0];
+        world.arm.update();
+
+        world.run_program().unwrap();
+        run(&mut world, 20.0);
+        assert!(world.take_events().is_empty());
+        assert!(world.cycle_time().is_some());
+        assert_eq!(world.joints().angles, vec![-20.0, 0.0, -160.0, 30.0]);
+    }
+
+    #[test]
+    fn rejects_moves_into_itself() {
+        let mut world = World::new();
+        let folded = JointState {
+            angles: vec![0.0, -180.0, -160.0],
+            claw: world.arm.joints.claw,
+        };
+        assert!(matches!(
+            world.start(folded, None),
+            Err(MotionError::SelfCollision { .. })
+        ));
+        assert!(!world.is_moving());
+        assert!(matches!(
+            world.take_events()[..],
+            [Event::SelfCollision { .. }]
+        ));
+    }
+
+    #[test]
+    fn rejects_moves_in_protective_stop() {
+        let mut world = World::new();
+        world.set_physics_mode(PhysicsMode::Simple);
+        while !world.is_protective_stop() && world.time() < 10.0 {
+            world.jog(0, false);
+            run(&mut world, 0.1);
+        }
+        assert!(world.is_protective_stop());
+        let target = Frame {
+            origin: Vec2::new(0.0, -0.26),
+            angle: -90.0,
+        };
+        assert_eq!(world.move_to(target), Err(MotionError::ProtectiveStop));
+        assert_eq!(world.return_to_base(), Err(MotionError::ProtectiveStop));
+        world.reset_protective_stop();
+        assert_eq!(world.return_to_base(), Ok(()));
+    }
+
+    #[test]
+    fn grasps_centered_object() {
+        let mut world = around_object(0.0);
+        for _ in 0..20 {
+            world.close_claws();
+        }
+        assert_eq!(world.grasped_object(), Some("obj0"));
+        assert!((world.grip_width() - 0.05).abs() < 0.005);
+    }
+
+    #[test]
+    fn grasps_off_center_object() {
+        for offset in [-0.005, 0.005] {
+            let mut world = around_object(offset);
+            for _ in 0..20 {
+                world.close_claws();
+            }
+            assert_eq!(world.grasped_object(), Some("obj0"));
+            assert!((world.grip_width() - 0.05).abs() < 0.005);
+            // the claws pushed the object onto their axis
+            let center = polygon_centroid(world.objects["obj0"].get_vertices_ref());
+            assert!((center.x + 0.05).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn program_releases_manual_grasp() {
+        let mut world = around_object(0.0);
+        for _ in 0..20 {
+            world.close_claws();
+        }
+        assert_eq!(world.grasped_object(), Some("obj0"));
+        world
+            .move_to(Frame {
+                origin: Vec2::new(-0.05, -0.26),
+                angle: -90.0,
+            })
+            .unwrap();
+        run(&mut world, 2.0);
+        let lifted = polygon_centroid(world.objects["obj0"].get_vertices_ref());
+        assert!(lifted.y > GROUND + 0.1);
+
+        // a waypoint with open claws at the current pose
+        world.teach();
+        world.jobs[0].joints.claw = world.arm.description.claw_limits().1;
+        world.jobs[0].moved_object = String::new();
+        world.run_program().unwrap();
+        run(&mut world, 3.0);
+        assert_eq!(world.grasped_object(), None);
+        assert!(!world.is_held("obj0"));
+        let lowest = world.objects["obj0"]
+            .get_vertices_ref()
+            .iter()
+            .map(|v| v.y)
+            .fold(f32::INFINITY, f32::min);
+        assert!((lowest - GROUND).abs() < 0.005);
+    }
+
+    #[test]
+    fn aborts_program_at_rejected_step() {
+        let mut world = World::new();
+        world.set_physics_mode(PhysicsMode::Simple);
+        let claw = world.arm.joints.claw;
+        let step = |angles: Vec<f32>, moved_object: &str| State {
+            joints: JointState { angles, claw },
+            moved_object: moved_object.to_string(),
+            zone: 0.0,
+        };
+        // the second step folds the arm into itself
+        world.jobs = vec![
+            step(vec![0.0, -180.0, -160.0], "obj"),
+            step(vec![0.0, -30.0, -90.0], ""),
+        ];
+        world.run_program().unwrap();
+        run(&mut world, 5.0);
+        assert!(!world.running);
+        assert!(!world.is_moving());
+        assert!(world.state.is_none());
+        assert!(!world.is_held("obj"));
+        assert_eq!(world.cycle_time(), None);
+        assert_eq!(world.joints().angles, vec![0.0, -30.0, -90.0]);
+        assert!(matches!(
+            world.take_events()[..],
+            [Event::SelfCollision { .. }]
+        ));
+
+        // the base joint cannot move, steps that turn it never end
+        world.arm.description.links[0].max_velocity = 0.0;
+        world.jobs = vec![step(vec![-20.0, -30.0, -90.0], "")];
+        assert!(matches!(
+            world.run_program(),
+            Err(MotionError::Infeasible { .. })
+        ));
+        assert!(world.jobs.is_empty() && world.state.is_none());
+        world.jobs = vec![
+            step(vec![-20.0, -30.0, -90.0], ""),
+            step(vec![0.0, -40.0, -90.0], ""),
+        ];
+        world.run_program().unwrap();
+        run(&mut world, 5.0);
+        assert!(!world.running && world.state.is_none());
+        assert!(matches!(
+            world.take_events()[..],
+            [Event::ProgramAborted {
+                error: MotionError::Infeasible { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn does_not_push_objects_into_fixtures() {
+        // the claws would push the object to the right, against a block on the ground
+        let mut world = around_object(-0.005);
+        let block = generate_fixture(
+            vec![
+                Vec2::new(-0.03, GROUND),
+                Vec2::new(0.0, GROUND),
+                Vec2::new(0.0, GROUND + 0.02),
+                Vec2::new(-0.03, GROUND + 0.02),
+            ],
+            [0.0; 3],
+        );
+        world.fixtures.insert("block".to_string(), block);
+        for _ in 0..20 {
+            world.close_claws();
+        }
+        assert_eq!(world.grasped_object(), None);
+        assert!(world.obstruction("obj0") <= GRIP_TOLERANCE);
+        let center = polygon_centroid(world.objects["obj0"].get_vertices_ref());
+        assert!((center.x + 0.055).abs() < 0.001);
+        assert!(!world.is_protective_stop());
+    }
+
+    #[cfg(feature = "rapier")]
+    #[test]
+    fn objects_land_in_both_physics_modes() {
+        for mode in [PhysicsMode::Simple, PhysicsMode::Rapier] {
+            let mut world = World::new();
+            world.set_physics_mode(mode);
+            assert_eq!(world.physics_mode(), mode);
+            world.objects.clear();
+            world.spawn_object((0.0, GROUND + 0.2), [0.0; 3]);
+            run(&mut world, 2.0);
+
+            let body = world.object_body("obj0").unwrap();
+            assert!(body.resting, "{:?}", mode);
+            let lowest = world.objects["obj0"]
+                .get_vertices_ref()
+                .iter()
+                .map(|v| v.y)
+                .fold(f32::INFINITY, f32::min);
+            assert!((lowest - GROUND).abs() < 0.002, "{:?} {}", mode, lowest);
+        }
+    }
+
+    #[test]
+    fn weak_grip_drops_the_object() {
+        let mut world = around_object(0.0);
+        for _ in 0..20 {
+            world.close_claws();
+        }
+        assert_eq!(world.grasped_object(), Some("obj0"));
+        // a program that carries the object up and to the side
+        for x in [-0.05, -0.1] {
+            world
+                .move_to(Frame {
+                    origin: Vec2::new(x, -0.26),
+                    angle: -90.0,
+                })
+                .unwrap();
+            run(&mut world, 2.0);
+            world.teach();
+        }
+        world.jobs.reverse();
+        world.run_program().unwrap();
+        run(&mut world, 0.2);
+        assert!(world.running);
+
+        // the claws no longer carry its weight
+        world.set_grip_force(0.1);
+        run(&mut world, 0.5);
+        assert_eq!(world.grasped_object(), None);
+        assert!(!world.running && !world.is_moving());
+        assert!(matches!(
+            &world.take_events()[..],
+            [Event::GraspLost { object, .. }] if object == "obj0"
+        ));
+        run(&mut world, 2.0);
+        let lowest = world.objects["obj0"]
+            .get_vertices_ref()
+            .iter()
+            .map(|v| v.y)
+            .fold(f32::INFINITY, f32::min);
+        assert!((lowest - GROUND).abs() < 0.005);
+    }
+
+    #[test]
+    fn spawned_objects_keep_configured_ones() {
+        let mut cell = CellDescription::default();
+        cell.objects[0].name = "obj0".to_string();
+        let mut world = World::from_description(ArmDescription::default(), cell);
+        let configured = world.objects["obj0"].get_vertices_ref().clone();
+        world.spawn_object((0.0, 0.0), [0.0; 3]);
+        world.spawn_object((0.1, 0.0), [0.0; 3]);
+        assert_eq!(world.objects.len(), 3);
+        assert_eq!(world.objects["obj0"].get_vertices_ref(), &configured);
+
+        // only the spawned objects are removed, the last one first
+        world.remove_last_object();
+        assert!(world.objects.contains_key("obj1") && !world.objects.contains_key("obj2"));
+        for _ in 0..3 {
+            world.remove_last_object();
+        }
+        assert_eq!(world.objects.keys().collect::<Vec<_>>(), ["obj0"]);
+        assert_eq!(world.objects["obj0"].get_vertices_ref(), &configured);
+    }
+}